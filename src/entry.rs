@@ -0,0 +1,596 @@
+//! The entry API for `Tree`, which gives in-place access to the value of a
+//! key whether or not the key is already present in the tree.
+//! 
+
+use std::borrow::Borrow;
+
+use crate::Tree;
+
+/// A view into a single entry of a `Tree`, which may be vacant or occupied.
+/// Created by `Tree::entry()`.
+/// 
+/// # Variants
+/// 
+/// * `Vacant`    - The key isn't in the tree.
+/// * `Occupied`  - The key is in the tree.
+/// 
+pub enum Entry<'a, K, V>
+{
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+/// A view into the entry of a `Tree` at, or following, a given key. It's
+/// occupied by the entry with the smallest key that's greater than or equal to
+/// the given key, or is vacant if there's no such entry. Created by
+/// `Tree::ceiling_entry()`.
+/// 
+/// # Variants
+/// 
+/// * `Vacant`    - No key in the tree is greater than or equal to the key.
+/// * `Occupied`  - The entry holding the ceiling of the key.
+/// 
+pub enum CeilingEntry<'a, K, V>
+{
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntryRef<'a, K, V>),
+}
+
+/// A view into a single entry of a `Tree`, looked up by a borrowed form of
+/// its key, which may be vacant or occupied. Created by `Tree::entry_ref()`.
+/// 
+/// # Variants
+/// 
+/// * `Vacant`    - The key isn't in the tree.
+/// * `Occupied`  - The key is in the tree.
+/// 
+pub enum EntryRef<'a, 'b, K, Q: ?Sized, V>
+{
+    Vacant(VacantEntryRef<'a, 'b, K, Q, V>),
+    Occupied(OccupiedEntryRef<'a, K, V>),
+}
+
+/// A view into a vacant entry of a `Tree`. It holds the key, and a reference
+/// to the tree the key will be inserted into.
+/// 
+pub struct VacantEntry<'a, K, V>
+{
+    pub(crate) tree : &'a mut Tree<K, V>,
+    pub(crate) key  : K,
+}
+
+/// A view into a vacant entry of a `Tree` that was looked up by a borrowed
+/// key. It holds the borrowed key, which is only converted into an owned key
+/// if a value is inserted, and a reference to the tree.
+/// 
+pub struct VacantEntryRef<'a, 'b, K, Q: ?Sized, V>
+{
+    pub(crate) tree : &'a mut Tree<K, V>,
+    pub(crate) key  : &'b Q,
+}
+
+/// A view into an occupied entry of a `Tree` that holds references to the key
+/// and the value in the tree. Created by `Tree::entry_ref()` when the borrowed
+/// key is present, and by `Tree::ceiling_entry()` for the ceiling of its key.
+/// 
+pub struct OccupiedEntryRef<'a, K, V>
+{
+    pub(crate) key   : &'a K,
+    pub(crate) value : &'a mut V,
+}
+
+/// A view into an occupied entry of a `Tree`. It holds the key that was
+/// looked up, and a reference to the value in the tree.
+/// 
+pub struct OccupiedEntry<'a, K, V>
+{
+    pub(crate) key   : K,
+    pub(crate) value : &'a mut V,
+}
+
+impl<'a, K, V> Entry<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    /// Inserts `default` if the entry is vacant, then returns a mutable
+    /// reference to the value of the entry.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// *tree.entry("foo").or_insert(1) += 10;
+    /// *tree.entry("foo").or_insert(1) += 10;
+    /// assert_eq!(tree[&"foo"], 21);
+    /// ```
+    ///
+    pub fn or_insert(self, default: V) -> &'a mut V
+    {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Inserts the value returned by `default` if the entry is vacant, then
+    /// returns a mutable reference to the value of the entry. The closure is
+    /// only invoked if the entry is vacant.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.entry("foo").or_insert_with(|| "bar".to_string()).push('!');
+    /// assert_eq!(tree[&"foo"], "bar!");
+    /// ```
+    ///
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Inserts the value returned by `default` if the entry is vacant, then
+    /// returns a mutable reference to the value of the entry. The closure is
+    /// given a reference to the key, so the value can be derived from it, and
+    /// is only invoked if the entry is vacant.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// *tree.entry("four").or_insert_with_key(|k| k.len()) += 10;
+    /// assert_eq!(tree[&"four"], 14);
+    /// ```
+    ///
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            },
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Inserts `V::default()` if the entry is vacant, then returns a mutable
+    /// reference to the value of the entry.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree: Tree<char, Vec<i32>> = Tree::new();
+    /// tree.entry('a').or_default().push(1);
+    /// tree.entry('a').or_default().push(2);
+    /// assert_eq!(tree[&'a'], vec![1, 2]);
+    /// ```
+    ///
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, K, V> CeilingEntry<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    /// Inserts `default` at the given key if the entry is vacant, then returns
+    /// a mutable reference to the value of the entry.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new_with_insert(3, 'c');
+    /// assert_eq!(*tree.ceiling_entry(2).or_insert('b'), 'c');
+    /// assert_eq!(*tree.ceiling_entry(4).or_insert('d'), 'd');
+    /// assert_eq!(tree.len(), 2);
+    /// ```
+    ///
+    pub fn or_insert(self, default: V) -> &'a mut V
+    {
+        match self {
+            CeilingEntry::Vacant(entry) => entry.insert(default),
+            CeilingEntry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Inserts the value returned by `default` at the given key if the entry
+    /// is vacant, then returns a mutable reference to the value of the entry.
+    /// The closure is only invoked if the entry is vacant.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.ceiling_entry(1).or_insert_with(Vec::new).push('a');
+    /// assert_eq!(tree[&1], vec!['a']);
+    /// ```
+    ///
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            CeilingEntry::Vacant(entry) => entry.insert(default()),
+            CeilingEntry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+}
+
+impl<'a, 'b, K, Q, V> EntryRef<'a, 'b, K, Q, V>
+where
+    K: Borrow<Q> + Clone + Ord,
+    Q: Ord + ToOwned<Owned = K> + ?Sized,
+    V: Clone,
+{
+    /// Inserts `default` if the entry is vacant, converting the borrowed key
+    /// into an owned one, then returns a mutable reference to the value of the
+    /// entry.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree: Tree<String, i32> = Tree::new();
+    /// *tree.entry_ref("foo").or_insert(1) += 10;
+    /// *tree.entry_ref("foo").or_insert(1) += 10;
+    /// assert_eq!(tree[&"foo".to_string()], 21);
+    /// ```
+    ///
+    pub fn or_insert(self, default: V) -> &'a mut V
+    {
+        match self {
+            EntryRef::Vacant(entry) => entry.insert(default),
+            EntryRef::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Inserts the value returned by `default` if the entry is vacant, then
+    /// returns a mutable reference to the value of the entry. The closure is
+    /// only invoked if the entry is vacant.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree: Tree<String, Vec<u8>> = Tree::new();
+    /// tree.entry_ref("foo").or_insert_with(Vec::new).push(1);
+    /// assert_eq!(tree[&"foo".to_string()], [1]);
+    /// ```
+    ///
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            EntryRef::Vacant(entry) => entry.insert(default()),
+            EntryRef::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Inserts `V::default()` if the entry is vacant, then returns a mutable
+    /// reference to the value of the entry.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree: Tree<String, u32> = Tree::new();
+    /// *tree.entry_ref("foo").or_default() += 1;
+    /// assert_eq!(tree[&"foo".to_string()], 1);
+    /// ```
+    ///
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, 'b, K, Q, V> VacantEntryRef<'a, 'b, K, Q, V>
+where
+    K: Borrow<Q> + Clone + Ord,
+    Q: Ord + ToOwned<Owned = K> + ?Sized,
+    V: Clone,
+{
+    /// Returns a reference to the borrowed key that would be inserted.
+    ///
+    pub fn key(&self) -> &'b Q
+    {
+        self.key
+    }
+
+    /// Converts the borrowed key into an owned key and inserts it into the
+    /// tree with the given value, then returns a mutable reference to the
+    /// value.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree: Tree<String, i32> = Tree::new();
+    /// if let EntryRef::Vacant(entry) = tree.entry_ref("foo") {
+    ///     *entry.insert(1) += 1;
+    /// }
+    /// assert_eq!(tree[&"foo".to_string()], 2);
+    /// ```
+    ///
+    pub fn insert(self, value: V) -> &'a mut V
+    {
+        self.tree.insert(self.key.to_owned(), value);
+        self.tree.root.get_key_value_mut_by(self.key).unwrap().1
+    }
+}
+
+impl<'a, K, V> OccupiedEntryRef<'a, K, V>
+{
+    /// Returns a reference to the key of the entry as it's held in the tree.
+    ///
+    pub fn key(&self) -> &K
+    {
+        self.key
+    }
+
+    /// Returns a reference to the value of the entry.
+    ///
+    pub fn get(&self) -> &V
+    {
+        self.value
+    }
+
+    /// Returns a mutable reference to the value of the entry.
+    ///
+    pub fn get_mut(&mut self) -> &mut V
+    {
+        self.value
+    }
+
+    /// Converts the entry into a mutable reference to its value, with the
+    /// lifetime of the borrow of the tree.
+    ///
+    pub fn into_mut(self) -> &'a mut V
+    {
+        self.value
+    }
+
+    /// Replaces the value of the entry, and returns the former value.
+    ///
+    pub fn insert(&mut self, value: V) -> V
+    {
+        std::mem::replace(self.value, value)
+    }
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    /// Returns a reference to the key that would be inserted.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree: Tree<&str, i32> = Tree::new();
+    /// if let Entry::Vacant(entry) = tree.entry("foo") {
+    ///     assert_eq!(entry.key(), &"foo");
+    /// }
+    /// ```
+    ///
+    pub fn key(&self) -> &K
+    {
+        &self.key
+    }
+
+    /// Takes back ownership of the key without inserting anything.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree: Tree<String, i32> = Tree::new();
+    /// if let Entry::Vacant(entry) = tree.entry("foo".to_string()) {
+    ///     assert_eq!(entry.into_key(), "foo");
+    /// }
+    /// assert!(tree.is_empty());
+    /// ```
+    ///
+    pub fn into_key(self) -> K
+    {
+        self.key
+    }
+
+    /// Inserts the key of the entry into the tree with the given value, and
+    /// returns a mutable reference to the value.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// if let Entry::Vacant(entry) = tree.entry("foo") {
+    ///     *entry.insert(1) += 1;
+    /// }
+    /// assert_eq!(tree[&"foo"], 2);
+    /// ```
+    ///
+    pub fn insert(self, value: V) -> &'a mut V
+    {
+        self.tree.insert(self.key.clone(), value);
+        self.tree.get_mut(&self.key).unwrap()
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V>
+{
+    /// Returns a reference to the key of the entry.
+    ///
+    pub fn key(&self) -> &K
+    {
+        &self.key
+    }
+
+    /// Returns a reference to the value of the entry.
+    ///
+    pub fn get(&self) -> &V
+    {
+        self.value
+    }
+
+    /// Returns a mutable reference to the value of the entry.
+    ///
+    pub fn get_mut(&mut self) -> &mut V
+    {
+        self.value
+    }
+
+    /// Converts the entry into a mutable reference to its value, with the
+    /// lifetime of the borrow of the tree.
+    ///
+    pub fn into_mut(self) -> &'a mut V
+    {
+        self.value
+    }
+
+    /// Replaces the value of the entry, and returns the former value.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new_with_insert("foo", 1);
+    /// if let Entry::Occupied(mut entry) = tree.entry("foo") {
+    ///     assert_eq!(entry.insert(2), 1);
+    /// }
+    /// assert_eq!(tree[&"foo"], 2);
+    /// ```
+    ///
+    pub fn insert(&mut self, value: V) -> V
+    {
+        std::mem::replace(self.value, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn or_default_accumulates() {
+        let mut tree: Tree<char, Vec<u32>> = Tree::new();
+        for (i, ch) in "abracadabra".chars().enumerate() {
+            tree.entry(ch).or_default().push(i as u32);
+        }
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree[&'a'], vec![0, 3, 5, 7, 10]);
+        assert_eq!(tree[&'b'], vec![1, 8]);
+        assert_eq!(tree[&'c'], vec![4]);
+        
+        let existing = tree.entry('d').or_default();
+        assert_eq!(existing, &mut vec![6]);
+        assert_eq!(tree.len(), 5);
+
+        assert_eq!(tree.entry('z').or_default(), &mut Vec::<u32>::new());
+        assert_eq!(tree.len(), 6);
+    }
+
+    #[test]
+    fn vacant_entry_key_before_insert() {
+        let mut tree = Tree::new();
+        let mut missing = Vec::new();
+        for word in ["to", "be", "or", "not", "to", "be"] {
+            match tree.entry(word.to_string()) {
+                Entry::Vacant(entry) => {
+                    missing.push(entry.key().clone());
+                    entry.insert(1);
+                },
+                Entry::Occupied(mut entry) => {
+                    *entry.get_mut() += 1;
+                },
+            }
+        }
+        assert_eq!(missing, ["to", "be", "or", "not"]);
+        assert_eq!(tree[&"to".to_string()], 2);
+
+        if let Entry::Vacant(entry) = tree.entry("skip".to_string()) {
+            assert_eq!(entry.into_key(), "skip");
+        }
+        assert_eq!(tree.len(), 4);
+    }
+
+    #[test]
+    fn or_insert_with_key_uses_key_length() {
+        let mut tree = Tree::new();
+        for word in ["apple", "fig", "banana", "fig"] {
+            let len = tree.entry(word.to_string())
+                          .or_insert_with_key(|k| k.len());
+            assert_eq!(*len, word.len());
+        }
+        *tree.entry("fig".to_string()).or_insert_with_key(|_| 0) *= 2;
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree[&"fig".to_string()], 6);
+        assert_eq!(tree[&"banana".to_string()], 6);
+    }
+
+    #[test]
+    fn ceiling_entry_fills_gaps() {
+        let mut tree = Tree::new();
+        for t in (0..50).step_by(10) {
+            tree.insert(t, 0);
+        }
+        match tree.ceiling_entry(15) {
+            CeilingEntry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), &20);
+                *entry.get_mut() += 1;
+            },
+            CeilingEntry::Vacant(_) => panic!("Expected an occupied entry."),
+        }
+        *tree.ceiling_entry(40).or_insert(100) += 1;
+        assert!(matches!(tree.ceiling_entry(41), CeilingEntry::Vacant(_)));
+
+        *tree.ceiling_entry(45).or_insert(100) += 1;
+        *tree.ceiling_entry(45).or_insert(100) += 1;
+        let items = tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        assert_eq!(items, vec![(0, 0), (10, 0), (20, 1), (30, 0), (40, 1),
+                               (45, 102)]);
+    }
+
+    #[test]
+    fn entry_ref_clones_key_only_when_vacant() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static CLONES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+        struct Key(u32);
+
+        impl Clone for Key {
+            fn clone(&self) -> Self {
+                CLONES.with(|c| c.set(c.get() + 1));
+                Key(self.0)
+            }
+        }
+
+        let clones = || CLONES.with(|c| c.get());
+        let mut tree = Tree::new();
+        tree.insert(Key(1), 10);
+        tree.insert(Key(2), 20);
+        let before = clones();
+
+        *tree.entry_ref(&Key(1)).or_insert(0) += 1;
+        match tree.entry_ref(&Key(2)) {
+            EntryRef::Occupied(mut entry) => {
+                assert_eq!(entry.key(), &Key(2));
+                assert_eq!(entry.insert(21), 20);
+            },
+            EntryRef::Vacant(_) => panic!("Expected an occupied entry."),
+        }
+        assert_eq!(clones(), before);
+
+        *tree.entry_ref(&Key(3)).or_insert(30) += 1;
+        assert_eq!(clones(), before + 1);
+        assert_eq!(tree.iter().map(|(k, v)| (k.0, *v)).collect::<Vec<_>>(),
+                   [(1, 11), (2, 21), (3, 31)]);
+
+        let mut words: Tree<String, usize> = Tree::new();
+        for word in "the cat and the hat".split(' ') {
+            *words.entry_ref(word).or_default() += 1;
+        }
+        assert_eq!(words.len(), 4);
+        assert_eq!(words[&"the".to_string()], 2);
+    }
+}
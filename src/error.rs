@@ -0,0 +1,149 @@
+//! The error types returned by the fallible methods of `Tree`. Each error
+//! implements `Display` and `std::error::Error`, and carries enough
+//! information to report why the operation failed.
+//! 
+
+use std::error::Error;
+use std::fmt;
+
+/// The error returned by `Tree::try_insert()` when the key is already present
+/// in the tree. It gives access to the value already in the tree, and hands
+/// back the key and value that were rejected.
+///
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub struct OccupiedError<'a, K, V>
+{
+    /// The key that was rejected.
+    pub key      : K,
+    /// The value already associated with the key in the tree.
+    pub existing : &'a mut V,
+    /// The value that was rejected.
+    pub value    : V,
+}
+
+impl<K, V> fmt::Display for OccupiedError<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    /// Describes the rejected key and value, and the value already present.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new_with_insert("foo", 1);
+    /// let err = tree.try_insert("foo", 2).unwrap_err();
+    /// let msg = "failed to insert 2, key \"foo\" already exists with value 1";
+    /// assert_eq!(err.to_string(), msg);
+    /// ```
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "failed to insert {:?}, key {:?} already exists with value \
+                   {:?}", self.value, self.key, self.existing)
+    }
+}
+
+impl<K, V> Error for OccupiedError<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+}
+
+/// The error returned by `Tree::try_get_nth()` when the requested index is
+/// beyond the end of the tree.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct IndexOutOfRange
+{
+    /// The index that was requested.
+    pub index : usize,
+    /// The number of items in the tree at the time of the request.
+    pub len   : usize,
+}
+
+impl fmt::Display for IndexOutOfRange
+{
+    /// Describes the requested index and the length of the tree.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[('a', 1), ('b', 2)]);
+    /// let err  = tree.try_get_nth(5).unwrap_err();
+    /// assert_eq!(err.to_string(), "index 5 is out of range for length 2");
+    /// ```
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "index {} is out of range for length {}", 
+               self.index, self.len)
+    }
+}
+
+impl Error for IndexOutOfRange {}
+
+/// The error returned by `Tree::try_from_pairs()` when a key occurs more than
+/// once in the input.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DuplicateKey<K>
+{
+    /// The first key found to be repeated.
+    pub key : K,
+}
+
+impl<K> fmt::Display for DuplicateKey<K>
+where
+    K: fmt::Debug,
+{
+    /// Describes the repeated key.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let err = Tree::try_from_pairs(vec![("foo", 1), ("foo", 2)]);
+    /// assert_eq!(err.unwrap_err().to_string(), "duplicate key \"foo\"");
+    /// ```
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "duplicate key {:?}", self.key)
+    }
+}
+
+impl<K> Error for DuplicateKey<K>
+where
+    K: fmt::Debug,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::error::Error;
+
+    #[test]
+    fn display_and_source() {
+        let mut tree = Tree::new();
+        for ch in "abc".chars() {
+            tree.insert(ch, ch as u32);
+        }
+        let err = tree.try_get_nth(3).unwrap_err();
+        assert_eq!(err.to_string(), "index 3 is out of range for length 3");
+        assert!(err.source().is_none());
+
+        let boxed: Box<dyn Error> = Box::new(err);
+        assert_eq!(boxed.to_string(), "index 3 is out of range for length 3");
+
+        let err = tree.try_insert('b', 0).unwrap_err();
+        assert_eq!(err.to_string(), 
+                   "failed to insert 0, key 'b' already exists with value 98");
+        assert!(err.source().is_none());
+
+        let err = Tree::try_from_pairs(vec![('x', 1), ('x', 2)]).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate key 'x'");
+        assert!(err.source().is_none());
+    }
+}
@@ -7,37 +7,125 @@
 //! 
 
 
+use std::borrow::Borrow;
 use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fmt::Write;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Bound;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::ops::Index;
 use std::ops::IndexMut;
+use std::ops::RangeBounds;
 
-use Tree::*;
+pub mod entry;
+pub mod error;
+pub mod monoid;
+
+pub use entry::CeilingEntry;
+pub use entry::Entry;
+pub use entry::EntryRef;
+pub use entry::OccupiedEntry;
+pub use entry::OccupiedEntryRef;
+pub use entry::VacantEntry;
+pub use entry::VacantEntryRef;
+pub use error::DuplicateKey;
+pub use error::IndexOutOfRange;
+pub use error::OccupiedError;
+pub use monoid::MonoidTree;
+
+use Link::*;
 
 /// Represents a node in the binary tree, that holds a key and value and 
 /// slots for the right and left sub-trees.
 /// 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Node<K, V>
 {
     key     : K,
     value   : V,
     weight  : isize,
-    left    : Tree<K, V>,
-    right   : Tree<K, V>,
+    height  : isize,
+    left    : Link<K, V>,
+    right   : Link<K, V>,
+}
+
+/// Holds the rotation counts of a tree. A `Tree` keeps a single `Stats` next
+/// to its root, outside of the nodes, and the rotations count themselves in it
+/// as they're performed. Without the `stats` feature, this is a zero-sized type
+/// and counts nothing.
+///
+#[derive(Clone, Copy, Debug, Default)]
+struct Stats
+{
+    #[cfg(feature = "stats")]
+    total : u64,
+
+    #[cfg(feature = "stats")]
+    since_rebalance : u64,
+}
+
+impl Stats
+{
+    /// Starts the count of rotations since the last rebalance over.
+    ///
+    fn restart(&mut self)
+    {
+        #[cfg(feature = "stats")]
+        {
+            self.since_rebalance = 0;
+        }
+    }
+
+    /// Counts a single rotation.
+    ///
+    fn tally(&mut self)
+    {
+        #[cfg(feature = "stats")]
+        {
+            self.total           += 1;
+            self.since_rebalance += 1;
+        }
+    }
 }
 
 impl<K, V> Node<K, V>
-where
-    K: Clone + Ord,
-    V: Clone,
 {
     /// Private constructor for `Node`. Takes a key and value.
     /// 
     fn new(key: K, value: V) -> Self
     {
-        Node { key, value, weight: 1, left: Empty, right: Empty }
+        Node::with_children(key, value, Empty, Empty)
+    }
+
+    /// Private constructor for `Node` that takes its sub-trees along with the
+    /// key and value. The weight and height of the node are calculated from
+    /// the sub-trees.
+    ///
+    fn with_children(key: K, value: V, left: Link<K, V>, right: Link<K, V>)
+        -> Self
+    {
+        let mut node = Node {
+            key, value, left, right,
+            weight : 0,
+            height : 0,
+        };
+        node.update_weight();
+        node
+    }
+
+    /// Recalculates the weight and height of the node from the stored weights
+    /// and heights of its children.
+    ///
+    fn update_weight(&mut self)
+    {
+        self.weight = 1 + self.left.weight_or_zero()
+                        + self.right.weight_or_zero();
+        self.height = 1 + self.left.height().max(self.right.height());
     }
 
     /// Returns a value indicating the difference in height between its left
@@ -49,51 +137,122 @@ where
     }
 }
 
-/// Represents the whole AVL binary tree externally. Internally, it's also the
-/// the implementation for the sub-trees that fill each node's right and left
-/// slot. It also implements `Deref` and `DerefMut` to render a reference to the
-/// `Node` it holds in it's `Filled` variant. A `Tree` is either occupied by
-/// a `Node` as indicated by the `Filled` variant, or it's `Empty`.
+/// The implementation of the sub-trees that fill each node's right and left
+/// slot, and of the root held by a `Tree`. It implements `Deref` and `DerefMut`
+/// to render a reference to the `Node` it holds in it's `Filled` variant. A
+/// `Link` is either occupied by a `Node` as indicated by the `Filled` variant,
+/// or it's `Empty`.
 /// 
 /// # Variants
 /// 
 /// * `Empty`   - Doesn't hold a node.
-/// * `Filled`  - Holds a `Node`, which in turn may hold other `Tree`s.
+/// * `Filled`  - Holds a `Node`, which in turn may hold other `Link`s.
 /// 
 #[derive(Debug)]
-pub enum Tree<K, V> 
+enum Link<K, V> 
 {
     Empty,
     Filled(Box<Node<K, V>>),
 }
+
+/// Represents the whole AVL binary tree. It holds the root of the tree along
+/// with the rotation counts of the whole tree, which are kept here rather than
+/// by the nodes, so no change to the shape of the tree can drop them. It also
+/// implements `Deref` and `DerefMut` to render a reference to the `Node` at
+/// its root.
+/// 
+/// # Panics
+/// 
+/// Dereferencing an empty tree panics. Use `.root_key_value()` to inspect the
+/// root of a tree that may be empty.
+/// 
+#[derive(Debug)]
+pub struct Tree<K, V> 
+{
+    root  : Link<K, V>,
+    stats : Stats,
+}
+
 impl<K, V> Tree<K, V>
 where 
     K: Clone + Ord,
     V: Clone,
 {
-    /// Creates a new `Tree` populated with a `Node` holding the given key and
-    /// value.
+    /// Creates a perfectly balanced `Tree` by cloning the keys and values from
+    /// a slice that's sorted in ascending order by key with no duplicate keys.
+    /// The tree is built by recursively taking the middle item of each part of
+    /// the slice as the root of its sub-tree, so no rotations are needed and
+    /// it's an `O(n)` operation. Debug builds panic if the slice isn't sorted.
     /// ```
     /// use avl_tree::*;
     ///
-    /// let tree = Tree::new_with_insert('a', 1);
-    /// assert_eq!(tree[&'a'], 1);
+    /// let data = [('a', 1), ('b', 2), ('c', 3)];
+    /// let tree = Tree::from_sorted_slice(&data);
+    /// assert_eq!(tree.len(), 3);
+    /// assert_eq!(tree[&'b'], 2);
     /// ```
-    /// 
-    pub fn new_with_insert(key: K, value: V) -> Self
+    ///
+    pub fn from_sorted_slice(slice: &[(K, V)]) -> Self
     {
-        Filled(Box::new(Node::new(key, value)))
+        debug_assert!(slice.windows(2).all(|w| w[0].0 < w[1].0),
+                      "Slice must be sorted with no duplicate keys.");
+
+        let len = slice.len();
+        Tree::from_root(Link::from_sorted_iter(&mut slice.iter().cloned(), len))
     }
 
-    /// Creates a new empty `Tree` - the `Tree::Empty` variant.
+    /// Creates a `Tree` from key/value pairs, failing if any key occurs more
+    /// than once rather than letting the later value overwrite the earlier.
+    /// The pairs may be in any order; the error holds the first key that's
+    /// found to be repeated, in the order of the input.
     /// ```
     /// use avl_tree::*;
-    /// assert_eq!(Tree::<String, ()>::new().is_empty(), true);
+    ///
+    /// let tree = Tree::try_from_pairs(vec![(2, 'b'), (1, 'a')]).unwrap();
+    /// assert_eq!(tree.len(), 2);
+    ///
+    /// let err = Tree::try_from_pairs(vec![(1, 'a'), (1, 'b')]).unwrap_err();
+    /// assert_eq!(err.key, 1);
     /// ```
     ///
-    pub fn new() -> Self
+    pub fn try_from_pairs<I>(iter: I) -> Result<Self, DuplicateKey<K>>
+    where
+        I: IntoIterator<Item = (K, V)>,
     {
-        Empty
+        let mut tree = Tree::new();
+        for (key, value) in iter {
+            if let Err(err) = tree.try_insert(key, value) {
+                return Err(DuplicateKey { key: err.key });
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Creates a perfectly balanced `Tree` from keys that are sorted in
+    /// ascending order with no duplicates, giving each key a clone of `value`.
+    /// Like `.from_sorted_slice()`, no rotations are needed, so it's an `O(n)`
+    /// operation. This is handy for initializing dense index maps. Debug
+    /// builds panic if the keys aren't sorted.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys(0..4, false);
+    /// assert_eq!(tree.len(), 4);
+    /// assert_eq!(tree[&3], false);
+    /// ```
+    ///
+    pub fn with_sorted_keys<I>(keys: I, value: V) -> Self
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let items = keys.into_iter()
+                        .map(|k| (k, value.clone()))
+                        .collect::<Vec<_>>();
+
+        debug_assert!(items.windows(2).all(|w| w[0].0 < w[1].0),
+                      "Keys must be sorted with no duplicates.");
+
+        Tree::from_root(Link::from_sorted_vec(items))
     }
 
     /// Indicates whether the `Tree` is populated or entirely empty.
@@ -105,7 +264,182 @@ where
     /// 
     pub fn is_empty(&self) -> bool 
     {
-        matches!(self, Empty)
+        matches!(self.root, Empty)
+    }
+
+    /// Returns the number of items in the tree. This is read directly from the
+    /// weight of the root node, so it's an `O(1)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// assert_eq!(tree.len(), 0);
+    /// tree.insert("foo", 1);
+    /// tree.insert("bar", 2);
+    /// assert_eq!(tree.len(), 2);
+    /// ```
+    ///
+    pub fn len(&self) -> usize
+    {
+        self.root.weight_or_zero() as usize
+    }
+
+    /// Returns the key and value held by the root node of the tree, or `None`
+    /// if the tree is empty. Unlike dereferencing the tree, this never panics.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::new_with_insert("foo", 1);
+    /// assert_eq!(tree.root_key_value(), Some((&"foo", &1)));
+    /// assert_eq!(Tree::<i32, i32>::new().root_key_value(), None);
+    /// ```
+    ///
+    pub fn root_key_value(&self) -> Option<(&K, &V)>
+    {
+        match &self.root {
+            Filled(node) => Some((&node.key, &node.value)),
+            Empty => None,
+        }
+    }
+
+    /// Returns the smallest key in the tree along with its value, or `None` if
+    /// the tree is empty.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for (i, ch) in "qwerty".chars().enumerate() {
+    ///     tree.insert(ch, i);
+    /// }
+    /// assert_eq!(tree.first_key_value(), Some((&'e', &2)));
+    /// ```
+    ///
+    pub fn first_key_value(&self) -> Option<(&K, &V)>
+    {
+        self.root.first_key_value()
+    }
+
+    /// Returns the largest key in the tree along with its value, or `None` if
+    /// the tree is empty.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for (i, ch) in "qwerty".chars().enumerate() {
+    ///     tree.insert(ch, i);
+    /// }
+    /// assert_eq!(tree.last_key_value(), Some((&'y', &5)));
+    /// ```
+    ///
+    pub fn last_key_value(&self) -> Option<(&K, &V)>
+    {
+        self.root.last_key_value()
+    }
+
+    /// Returns a mutable reference to the value of the smallest key in the
+    /// tree, or `None` if the tree is empty. The node isn't moved, so the
+    /// shape of the tree is unaffected.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for (i, ch) in "qwerty".chars().enumerate() {
+    ///     tree.insert(ch, i);
+    /// }
+    /// *tree.first_value_mut().unwrap() += 10;
+    /// assert_eq!(tree[&'e'], 12);
+    /// ```
+    ///
+    pub fn first_value_mut(&mut self) -> Option<&mut V>
+    {
+        let mut t = &mut self.root;
+        while let Filled(node) = t {
+            if !node.left.is_filled() {
+                return Some(&mut node.value);
+            }
+            t = &mut node.left;
+        }
+        None
+    }
+
+    /// Returns a mutable reference to the value of the largest key in the
+    /// tree, or `None` if the tree is empty. The node isn't moved, so the
+    /// shape of the tree is unaffected.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for (i, ch) in "qwerty".chars().enumerate() {
+    ///     tree.insert(ch, i);
+    /// }
+    /// *tree.last_value_mut().unwrap() += 10;
+    /// assert_eq!(tree[&'y'], 15);
+    /// ```
+    ///
+    pub fn last_value_mut(&mut self) -> Option<&mut V>
+    {
+        let mut t = &mut self.root;
+        while let Filled(node) = t {
+            if !node.right.is_filled() {
+                return Some(&mut node.value);
+            }
+            t = &mut node.right;
+        }
+        None
+    }
+
+    /// Returns an estimate of the number of heap bytes used by the tree. Each
+    /// node is allocated in its own `Box`, and the `Box` pointers to a node's
+    /// children are held within the node itself, so the estimate is the number
+    /// of items times the size of a `Node`. Heap memory owned by the keys and
+    /// values themselves, and any allocator bookkeeping, isn't included.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// assert_eq!(tree.approx_heap_bytes(), 0);
+    /// tree.insert(1_u64, 1_u64);
+    /// assert!(tree.approx_heap_bytes() >= 16);
+    /// ```
+    ///
+    pub fn approx_heap_bytes(&self) -> usize
+    {
+        self.len() * std::mem::size_of::<Node<K, V>>()
+    }
+
+    /// Returns an iterator over the keys and values of the tree in ascending
+    /// order by key.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for ch in "hello".chars() {
+    ///     tree.insert(ch, ());
+    /// }
+    /// let keys = tree.iter().map(|(k, _)| *k).collect::<String>();
+    /// assert_eq!(keys, "ehlo");
+    /// ```
+    ///
+    pub fn iter(&self) -> Iter<'_, K, V>
+    {
+        Iter::new(&self.root)
+    }
+
+    /// Returns an iterator over the keys of the tree, along with mutable
+    /// references to their values, in ascending order by key.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 10), (2, 20)]);
+    /// for (_, v) in tree.iter_mut() {
+    ///     *v += 1;
+    /// }
+    /// assert_eq!(tree[&2], 21);
+    /// ```
+    ///
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V>
+    {
+        IterMut::new(&mut self.root)
     }
 
     /// Retrieves the value associated with the given key. If the key exists in
@@ -122,8 +456,8 @@ where
     /// 
     pub fn get(&self, key: &K) -> Option<&V>
     {
-        match self {
-            Filled(_) => self.get_internal(key),
+        match self.root {
+            Filled(_) => self.root.get_internal(key),
             _ => None,
         }
     }
@@ -141,73 +475,37 @@ where
     ///
     pub fn get_mut(&mut self, key: &K) -> Option<&mut V>
     {
-        match self {
-            Filled(_) => self.get_mut_internal(key),
-            _ => None,
-        }
+        self.root.get_mut(key)
     }
     
-    /// Inserts the given key and value into the binary tree. If the key was
-    /// already present, then `Some(V)` is returned holding the former value
-    /// of the key. If the key wasn't already present, `None` is returned.
+    /// Inserts the given key and value only if the key isn't already present.
+    /// On success, a mutable reference to the newly inserted value is
+    /// returned. If the key was already present, the tree is left unchanged
+    /// and an `OccupiedError` is returned, which holds a mutable reference to
+    /// the existing value along with the rejected key and value.
     /// ```
     /// use avl_tree::*;
-    /// 
+    ///
     /// let mut tree = Tree::new();
-    /// assert_eq!(tree.insert("foo", 42), None);
-    /// assert_eq!(tree.insert("foo", 72), Some(42));
-    /// assert_eq!(tree[&"foo"], 72);
+    /// assert_eq!(tree.try_insert("foo", 42).map(|v| *v), Ok(42));
+    ///
+    /// let err = tree.try_insert("foo", 72).unwrap_err();
+    /// assert_eq!(*err.existing, 42);
+    /// assert_eq!(err.value, 72);
     /// ```
     ///
-    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    pub fn try_insert(&mut self, key: K, value: V) 
+        -> Result<&mut V, OccupiedError<'_, K, V>>
     {
-        use Ordering::*;
-        let mut ret = None;
-        match self {
-            Empty => {
-                *self = Tree::new_with_insert(key, value);
+        match self.root.insert_new_within(key, value, 1, &mut self.stats) {
+            Ok(index) => {
+                Ok(self.root.get_nth_value_mut(index).unwrap())
             },
-            Filled(node) => {
-                match key.cmp(&node.key) {
-                    Less => {
-                        ret = node.left.insert(key, value);
-                    },
-                    Greater => {
-                        ret = node.right.insert(key, value);
-                    },
-                    Equal => {
-                        ret = Some(node.value.clone());
-                        node.value = value;
-                    },
-                }
-                // If ret.is_none() == true, tree changed size.
-                if ret.is_none() {
-                    node.weight += 1;
-
-                    let bf   = node.balance();
-                    let bf_r = node.right.balance();
-                    let bf_l = node.left.balance();
-
-                    if bf >= 2 {
-                        if bf_l > 0 {
-                            self.rotate_left_left();
-                        } 
-                        else if bf_l < 0 {
-                            self.rotate_left_right();
-                        }
-                    }
-                    else if bf <= -2 {
-                        if bf_r < 0 {
-                            self.rotate_right_right();
-                        } 
-                        else if bf_r > 0 {
-                            self.rotate_right_left();
-                        }
-                    }
-                }
+            Err((key, value)) => {
+                let existing = self.get_mut(&key).unwrap();
+                Err(OccupiedError { key, existing, value })
             },
         }
-        ret
     }
 
     /// Removes the provided key from the binary tree. If the key was present
@@ -226,62 +524,28 @@ where
     ///
     pub fn remove(&mut self, key: &K) -> Option<V>
     {
-        let mut ret = None;
+        self.take(key)
+    }
 
-        if let Filled(node) = self {
-            if key == &node.key {
-                ret = Some(node.value.clone());
-                if node.left.is_empty() && node.right.is_empty() {
-                    *self = Empty;
-                }
-                else if node.left.is_filled() {
-                    let (k, v)   = node.left.predecessor();
-                    node.key     = k.clone();
-                    node.value   = v;
-                    node.weight -= 1;
-                    node.left.remove(&k);
-                } 
-                else {
-                    let (k, v)   = node.right.successor();
-                    node.key     = k.clone();
-                    node.value   = v;
-                    node.weight -= 1;
-                    node.right.remove(&k);
-                }                
-            } else {
-                if key < &node.key {
-                    ret = node.left.remove(key);
-                }
-                else if key > &node.key {
-                    ret = node.right.remove(key);
-                }
-                if ret.is_some() {
-                    node.weight -= 1;
-                    
-                    let bf   = node.balance();
-                    let bf_r = node.right.balance();
-                    let bf_l = node.left.balance();
-                    
-                    if bf >= 2 {
-                        if bf_l >= 0 {
-                            self.rotate_left_left();
-                        }
-                        else if bf_l < 0 {
-                            self.rotate_left_right();
-                        }
-                    }
-                    else if bf <= -2 {
-                        if bf_r <= 0 {
-                            self.rotate_right_right();
-                        }
-                        else if bf_r > 0 {
-                            self.rotate_right_left();
-                        }
-                    }
-                }
-            }
-        }
-        ret
+    /// Removes each of the given keys from the tree, rebalancing as it goes.
+    /// Keys that aren't in the tree are skipped. Returns the number of keys
+    /// that were actually removed.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for ch in "qwerty".chars() {
+    ///     tree.insert(ch, ());
+    /// }
+    /// assert_eq!(tree.remove_all("wxyz".chars()), 2);
+    /// assert_eq!(tree.len(), 4);
+    /// ```
+    ///
+    pub fn remove_all<I>(&mut self, keys: I) -> usize
+    where
+        I: IntoIterator<Item = K>,
+    {
+        keys.into_iter().filter(|key| self.remove(key).is_some()).count()
     }
 
     /// Returns the key and value in the tree at the ordinal 0-based position 
@@ -305,380 +569,6129 @@ where
     ///
     pub fn get_nth(&self, index: usize) -> Option<(&K, &V)>
     {
-        match self {
-            Filled(_) => self.get_nth_internal(index as isize),
+        match &self.root {
+            Filled(_) => self.root.get_nth_internal(index as isize),
             _ => None,
         }
     }
 
-    /// Internal implementation for `.find_nth()`. The public facing version
-    /// prohibits passing negative values as indices, while the internal version
-    /// needs flexibility to avoid unsigned overflows.
-    /// 
-    fn get_nth_internal(&self, index: isize) -> Option<(&K, &V)>
+    /// Returns an iterator over the items whose ordinal 0-based positions fall
+    /// within the half-open range `[start, end)`. The iterator is positioned
+    /// at `start` in `O(log n)` time, then walks forward from there. Bounds
+    /// beyond the length of the tree are clamped, and an empty iterator is
+    /// returned if `start >= end`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for ch in "qwertyuiop".chars() {
+    ///     tree.insert(ch, ());
+    /// }
+    /// let page = tree.iter_index_range(2, 5).map(|(k, _)| *k);
+    /// assert_eq!(page.collect::<String>(), "opq");
+    /// assert_eq!(tree.iter_index_range(8, 100).count(), 2);
+    /// ```
+    ///
+    pub fn iter_index_range(&self, start: usize, end: usize) 
+        -> impl Iterator<Item = (&K, &V)>
     {
-        use Ordering::*;
-        
-        let mut ret  = None;
-        let     wt_l = match &self.left { Filled(node) => node.weight, 
-                                          Empty        => 0,           };
-        let idx_adj = index - wt_l;
-        
-        match idx_adj.cmp(&0_isize) {
-            Equal => { 
-                ret = Some((&self.key, &self.value)); 
-            },
-            Greater => {
-                if self.right.is_filled() {
-                    ret = self.right.get_nth_internal(idx_adj - 1);
-                }
-            },
-            Less => {
-                if self.left.is_filled() {
-                    ret = self.left.get_nth_internal(index);
-                }
-            },
-        }
-        ret
+        let end = end.min(self.len());
+        Iter::new_at_nth(&self.root, start).take(end.saturating_sub(start))
     }
 
-    /// Internal implementation for `.get()`. Returns the value corresponding
-    /// to the given key. Doesn't check whether tree is empty.
+    /// Indicates whether every key in this tree is also present in `other`.
+    /// Only the keys are compared. The in-order keys of both trees are walked
+    /// together, so this is an `O(n + m)` operation.
+    /// ```
+    /// use avl_tree::*;
     ///
-    fn get_internal(&self, key: &K) -> Option<&V>
+    /// let mut t1 = Tree::new();
+    /// let mut t2 = Tree::new();
+    /// for ch in "abc".chars()   { t1.insert(ch, 1); }
+    /// for ch in "abcde".chars() { t2.insert(ch, 2); }
+    ///
+    /// assert_eq!(t1.is_subset(&t2), true);
+    /// assert_eq!(t2.is_subset(&t1), false);
+    /// ```
+    ///
+    pub fn is_subset(&self, other: &Tree<K, V>) -> bool
     {
-        use Ordering::*;
-        let mut ret = None;
-        match key.cmp(&self.key) {
-            Less => {
-                if self.left.is_filled() {
-                    ret = self.left.get_internal(key);
-                }
-            },
-            Greater => {
-                if self.right.is_filled() {
-                    ret = self.right.get_internal(key);
-                }
-            },
-            Equal => {
-                ret = Some(&self.value)
-            },
+        if self.len() > other.len() {
+            return false;
         }
-        ret
+        let mut others = other.iter().map(|(k, _)| k);
+        self.iter().all(|(k, _)| others.find(|&o| o >= k) == Some(k))
     }
 
-    /// Internal implementation for `.get_mut()`. Returns a mutable reference
-    /// to the corresponding value of the key. Doesn't check whether tree is
-    /// empty or not before executing search.
+    /// Indicates whether every key in `other` is also present in this tree.
+    /// This is the same as `other.is_subset(self)`.
+    /// ```
+    /// use avl_tree::*;
     ///
-    fn get_mut_internal(&mut self, key: &K) -> Option<&mut V>
+    /// let mut t1 = Tree::new();
+    /// let mut t2 = Tree::new();
+    /// for ch in "abc".chars()   { t1.insert(ch, 1); }
+    /// for ch in "abcde".chars() { t2.insert(ch, 2); }
+    ///
+    /// assert_eq!(t2.is_superset(&t1), true);
+    /// assert_eq!(t1.is_superset(&t2), false);
+    /// ```
+    ///
+    pub fn is_superset(&self, other: &Tree<K, V>) -> bool
     {
-        use Ordering::*;
-        let mut ret = None;
-        match key.cmp(&self.key) {
-            Less => {
-                if self.left.is_filled() {
-                    ret = self.left.get_mut_internal(key);
-                }
-            },
-            Greater => {
-                if self.right.is_filled() {
-                    ret = self.right.get_mut_internal(key);
-                }
-            },
-            Equal => {
-                ret = Some(&mut self.value);
-            }
-        }
-        ret
+        other.is_subset(self)
     }
 
-    /// Returns the height of the tree, which is the log2 of the number of nodes
-    /// and sub-nodes in the current `Tree`.
-    /// 
-    fn height(&self) -> isize
+    /// Removes the key and value at the ordinal 0-based position given by
+    /// `index`, and returns them as `Some((K, V))`. If `index` is out of range,
+    /// `None` is returned and the tree is left unchanged. This is the mutating
+    /// counterpart to `.get_nth()`, and is also an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for (i, ch) in "qwerty".chars().enumerate() {
+    ///     tree.insert(ch, i);
+    /// }
+    /// assert_eq!(tree.pop_nth(1), Some(('q', 0)));
+    /// assert_eq!(tree.pop_nth(9), None);
+    /// assert_eq!(tree.len(), 5);
+    /// ```
+    ///
+    pub fn pop_nth(&mut self, index: usize) -> Option<(K, V)>
     {
-        match self {
-            Filled(node) => Self::floor_log2(node.weight),
-            Empty => 0,
+        if index < self.len() {
+            Some(self.root.pop_nth_internal(index, &mut self.stats))
+        } else {
+            None
         }
     }
 
-    /// A simple, but quick, calculation for `floor(log2(n))`.
-    /// 
-    fn floor_log2(mut n: isize) -> isize
+    /// Appends a batch of key/value pairs that is already sorted in ascending
+    /// order, and whose keys are all greater than the largest key currently in
+    /// the tree. Rather than inserting each item individually, the batch is
+    /// built into a balanced sub-tree that is then joined to the right side of
+    /// the existing tree, avoiding most of the rotations. If the batch doesn't
+    /// meet these conditions, a debug build will panic, while a release build
+    /// falls back on inserting the items one by one.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.insert_sorted_batch((0..10).map(|i| (i, i * 2)));
+    /// tree.insert_sorted_batch((10..20).map(|i| (i, i * 2)));
+    ///
+    /// assert_eq!(tree[&15], 30);
+    /// assert_eq!(tree.get_nth(19), Some((&19, &38)));
+    /// ```
+    ///
+    pub fn insert_sorted_batch<I>(&mut self, batch: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
     {
-        if n != 0 {
-            let mut c = 0;
-            while n != 0 {
-                n >>= 1;
-                c  += 1;
+        let batch  = batch.into_iter().collect::<Vec<_>>();
+        let sorted = batch.windows(2).all(|w| w[0].0 < w[1].0);
+        let above  = match (self.last_key_value(), batch.first()) {
+            (Some((max, _)), Some((first, _))) => max < first,
+            _ => true,
+        };
+        debug_assert!(sorted && above,
+                      "Batch must be sorted and greater than the tree's keys.");
+
+        if !(sorted && above) {
+            for (key, value) in batch {
+                self.insert(key, value);
             }
-            c - 1
-        } else {
-            0
+        } else if !batch.is_empty() {
+            let     len          = batch.len();
+            let mut items        = batch.into_iter();
+            let     (key, value) = items.next().unwrap();
+            let     right        = Link::from_sorted_iter(&mut items, len - 1);
+            let     left         = self.root.take_tree();
+            let     stats        = &mut self.stats;
+            self.root = Link::join_pivot(left, key, value, right, stats);
         }
     }
 
-    /// Returns a value indicating whether the tree is balanced or not, with
-    /// negative values indicating the tree is heavy on the right, and
-    /// positive values indicating the tree is heavy on the left. The value 0
-    /// indicates a perfectly balanced tree/sub-tree.
-    /// 
-    fn balance(&self) -> isize 
+    /// Returns the item at the given percentile of the tree's ordering, where
+    /// `p` ranges from `0.0` (the smallest key) to `1.0` (the largest key).
+    /// The percentile is mapped to the ordinal position 
+    /// `round(p * (len - 1))`, and the item there is found with `.get_nth()`.
+    /// `None` is returned if the tree is empty, or if `p` is outside the range
+    /// `[0.0, 1.0]` or is `NaN`; it isn't clamped.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for ms in [12, 7, 30, 18, 25] {
+    ///     tree.insert(ms, ());
+    /// }
+    /// assert_eq!(tree.percentile(0.5), Some((&18, &())));
+    /// assert_eq!(tree.percentile(1.5), None);
+    /// ```
+    ///
+    pub fn percentile(&self, p: f64) -> Option<(&K, &V)>
     {
-        match self {
-            Filled(node) => node.left.height() - node.right.height(),
-            Empty => 0,
+        if self.is_empty() || !(0.0..=1.0).contains(&p) {
+            return None;
         }
+        let index = (p * (self.len() - 1) as f64).round() as usize;
+        self.get_nth(index)
     }
 
-    /// Moves the tree from it's former location, replacing it with `Empty` and
-    /// returns the moved value to the caller giving it ownership.
+    /// Swaps the values associated with the keys `a` and `b` in place. If
+    /// either key isn't in the tree, nothing is changed and `false` is
+    /// returned; otherwise, `true` is returned. The values are moved rather
+    /// than cloned, and no nodes are moved.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.insert("foo", 1);
+    /// tree.insert("bar", 2);
+    ///
+    /// assert_eq!(tree.swap_values(&"foo", &"bar"), true);
+    /// assert_eq!((tree[&"foo"], tree[&"bar"]), (2, 1));
+    /// assert_eq!(tree.swap_values(&"foo", &"baz"), false);
+    /// ```
     ///
-    fn take(&mut self) -> Tree<K, V>
+    pub fn swap_values(&mut self, a: &K, b: &K) -> bool
     {
-        std::mem::take(self)
+        if a == b {
+            return self.get(a).is_some();
+        }
+        match self.root.get_two_mut(a, b) {
+            Some((va, vb)) => {
+                std::mem::swap(va, vb);
+                true
+            },
+            None => false,
+        }
     }
 
-    /// Indicates whether a `Tree` has nodes (`true`), or is `Empty` (`false`). 
-    /// 
-    fn is_filled(&self) -> bool
+    /// Returns the key and value at the ordinal 0-based position `index`, like
+    /// `.get_nth()`, but reports an out of range index as an error holding the
+    /// requested index and the length of the tree.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[('a', 1), ('b', 2)]);
+    /// assert_eq!(tree.try_get_nth(1), Ok((&'b', &2)));
+    ///
+    /// let err = tree.try_get_nth(5).unwrap_err();
+    /// assert_eq!((err.index, err.len), (5, 2));
+    /// ```
+    ///
+    pub fn try_get_nth(&self, index: usize) 
+        -> Result<(&K, &V), IndexOutOfRange>
     {
-        matches!(self, Filled(_))
+        self.get_nth(index).ok_or(IndexOutOfRange { index, len: self.len() })
     }
 
-    /// Performs a left-left rotation on the current `Tree`. These methods are
-    /// used to keep the tree in balance, so both left and right sub-trees 
-    /// grow or shrink at nearly the same rate. The name of the method can
-    /// be read as, "a left rotation is performed on the left branch." The
-    /// node within the current tree will be updated to hold the former left
-    /// node.
-    /// 
-    fn rotate_left_left(&mut self)
+    /// Walks the keys of this tree and `other` together in ascending order,
+    /// pairing up the entries that share a key. Each item yielded holds a key
+    /// along with a `JoinSide` telling whether the key is only in this tree
+    /// (`Left`), only in `other` (`Right`), or in both (`Both`). No collections
+    /// are built, and the whole walk is an `O(n + m)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let t1 = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// let t2 = Tree::from_sorted_slice(&[(2, "two"), (3, "three")]);
+    ///
+    /// let joined = t1.join(&t2).collect::<Vec<_>>();
+    /// assert_eq!(joined, vec![(&1, JoinSide::Left(&'a')),
+    ///                         (&2, JoinSide::Both(&'b', &"two")),
+    ///                         (&3, JoinSide::Right(&"three"))]);
+    /// ```
+    ///
+    pub fn join<'a, W>(&'a self, other: &'a Tree<K, W>) 
+        -> impl Iterator<Item = (&'a K, JoinSide<&'a V, &'a W>)>
+    where
+        W: Clone,
     {
-        let mut n = self.take();
-        let mut t = n.left.take();
-        n.left    = t.right.take();
-        t.right   = n;
-        *self     = t;
-        self.update_weights(2);
+        use Ordering::*;
+        use JoinSide::*;
+
+        let mut lefts  = self.iter().peekable();
+        let mut rights = other.iter().peekable();
+
+        std::iter::from_fn(move || {
+            let ord = match (lefts.peek(), rights.peek()) {
+                (Some((kl, _)), Some((kr, _))) => kl.cmp(kr),
+                (Some(_),       None         ) => Less,
+                (None,          Some(_)      ) => Greater,
+                (None,          None         ) => return None,
+            };
+            match ord {
+                Less => {
+                    lefts.next().map(|(k, v)| (k, Left(v)))
+                },
+                Greater => {
+                    rights.next().map(|(k, w)| (k, Right(w)))
+                },
+                Equal => {
+                    let (k, v) = lefts.next()?;
+                    let (_, w) = rights.next()?;
+                    Some((k, Both(v, w)))
+                },
+            }
+        })
     }
 
-    /// Performs a right-right rotation on the current `Tree`. The `Tree`'s
-    /// node will be updated to hold the former right node.
-    /// 
-    fn rotate_right_right(&mut self)
+    /// Rebuilds the tree into a perfectly balanced shape, lowering its height
+    /// to the minimum possible for the number of items it holds. The items are
+    /// moved out in order, then the tree is rebuilt from them without any
+    /// rotations. This is an `O(n)` operation that's worth calling explicitly
+    /// after many removals when lookup speed is the priority.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for i in 0..100 {
+    ///     tree.insert(i, i);
+    /// }
+    /// tree.rebalance();
+    /// assert_eq!(tree.get_nth(50), Some((&50, &50)));
+    /// ```
+    ///
+    pub fn rebalance(&mut self)
     {
-        let mut n = self.take();
-        let mut t = n.right.take();
-        n.right   = t.left.take();
-        t.left    = n;
-        *self     = t;
-        self.update_weights(2);
+        let mut items = Vec::with_capacity(self.len());
+        self.root.take_tree().drain_into(&mut items);
+        self.root = Link::from_sorted_vec(items);
+        self.stats.restart();
     }
 
-    /// Performs a right-left rotation on the current `Tree`.
-    /// 
-    fn rotate_right_left(&mut self)
+    /// Visits every entry in ascending order by key, passing a mutable
+    /// reference to each value to `f`. Entries for which `f` returns `false`
+    /// are removed, while any changes made to the values of the others are
+    /// kept. The tree is rebuilt in balance afterward, so this is an `O(n)`
+    /// operation regardless of how many entries are removed.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut ttls = Tree::from_sorted_slice(&[("a", 1), ("b", 3), ("c", 2)]);
+    /// ttls.retain_mut(|_, ttl| {
+    ///     *ttl -= 1;
+    ///     *ttl > 0
+    /// });
+    /// assert_eq!(ttls.get(&"a"), None);
+    /// assert_eq!(ttls[&"b"], 2);
+    /// assert_eq!(ttls[&"c"], 1);
+    /// ```
+    ///
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
     {
-        let mut n  = self.take();
-        let mut t2 = n.right.left.take();
-        let mut t1 = n.right.take();
-        n.right    = t2.left.take();
-        t1.left    = t2.right.take();
-        t2.left    = n;
-        t2.right   = t1;
-        *self      = t2;
-        self.update_weights(2);
+        let mut items = Vec::with_capacity(self.len());
+        self.root.take_tree().drain_into(&mut items);
+        items.retain_mut(|(k, v)| f(k, v));
+        self.root = Link::from_sorted_vec(items);
     }
 
-    /// Performs a left-right rotation on the current `Tree`.
-    /// 
-    fn rotate_left_right(&mut self)
+    /// Reduces the entries of the tree to a single value by visiting them in
+    /// ascending order by key, passing the accumulated value along with each
+    /// key and value to `f`. The tree is walked recursively, so no iterator
+    /// is built.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[('a', 1), ('b', 2), ('c', 3)]);
+    /// assert_eq!(tree.fold(0, |sum, _, v| sum + v), 6);
+    ///
+    /// let s = tree.fold(String::new(), |mut s, k, _| { s.push(*k); s });
+    /// assert_eq!(s, "abc");
+    /// ```
+    ///
+    pub fn fold<B, F>(&self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, &K, &V) -> B,
     {
-        let mut n  = self.take();
-        let mut t2 = n.left.right.take();
-        let mut t1 = n.left.take();
-        n.left     = t2.right.take();
-        t1.right   = t2.left.take();
-        t2.right   = n;
-        t2.left    = t1;
-        *self      = t2;
-        self.update_weights(2);
-    } 
+        self.root.fold_internal(init, &mut f)
+    }
 
-    /// Updates the weights of a sub-tree by descending `depth` levels in the
-    /// tree to find valid values, which are then used to update the nodes
-    /// in the higher ranks. This is invoked after rotations.
-    /// 
-    fn update_weights(&mut self, depth: isize) -> isize
+    /// Returns a `Cursor` positioned at `key` if it's present in the tree, or
+    /// at the smallest key greater than `key` otherwise. If there's no such
+    /// key, the cursor is positioned past the end of the tree. The cursor can
+    /// then be stepped forward and backward through the tree in sorted order.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (3, 'c'), (5, 'e')]);
+    /// let mut cursor = tree.cursor_at(&2);
+    ///
+    /// assert_eq!(cursor.key_value(), Some((&3, &'c')));
+    /// cursor.move_next();
+    /// assert_eq!(cursor.key_value(), Some((&5, &'e')));
+    /// cursor.move_prev();
+    /// cursor.move_prev();
+    /// assert_eq!(cursor.key_value(), Some((&1, &'a')));
+    /// ```
+    ///
+    pub fn cursor_at(&self, key: &K) -> Cursor<'_, K, V>
     {
-        if depth >= 0 {
-            let mut wt_l = 0;
-            let mut wt_r = 0;
-            if self.left.is_filled() {
-                wt_l = self.left.update_weights(depth - 1);
-            }
-            if self.right.is_filled() {
-                wt_r = self.right.update_weights(depth - 1);
+        use Ordering::*;
+        let mut path     = Vec::new();
+        let mut ceil_len = 0;
+        let mut t        = &self.root;
+
+        while let Filled(node) = t {
+            path.push(&**node);
+            match key.cmp(&node.key) {
+                Less => {
+                    ceil_len = path.len();
+                    t = &node.left;
+                },
+                Greater => {
+                    t = &node.right;
+                },
+                Equal => {
+                    return Cursor { root: &self.root, path };
+                },
             }
-            self.weight = 1 + wt_l + wt_r;
         }
-        self.weight
+        path.truncate(ceil_len);
+        Cursor { root: &self.root, path }
     }
 
-    /// Returns the key and value of the rightmost node in the current `Tree`.
-    /// This is invoked as part of the `.remove()` method.
-    /// 
-    fn predecessor(&self) -> (K, V)
+    /// Returns a clone of the value associated with `key`, or `V::default()`
+    /// if the key isn't in the tree. The tree isn't modified either way.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::new_with_insert("apples", 3);
+    /// assert_eq!(tree.get_or_default(&"apples"), 3);
+    /// assert_eq!(tree.get_or_default(&"pears"), 0);
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    ///
+    pub fn get_or_default(&self, key: &K) -> V
+    where
+        V: Default,
     {
-        let mut t = self;
-        while let Filled(_) = t.right {
-            t = &t.right;
-        }
-        (t.key.clone(), t.value.clone())
+        self.get(key).cloned().unwrap_or_default()
     }
 
-    /// Returns the key and value of the leftmost node in the current `Tree`.
-    /// Invoked by `.remove()`.
-    /// 
-    fn successor(&self) -> (K, V)
+    /// Splits the tree by ordinal position, leaving the smallest `index` items
+    /// in this tree and returning the rest as a new tree. If `index` is equal
+    /// to or greater than the length of the tree, the returned tree is empty.
+    /// Both trees are left in balance. The split is done by recursively
+    /// joining the sub-trees on either side of the split point, which makes
+    /// it an `O(log n)` operation overall.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for ch in "qwerty".chars() {
+    ///     tree.insert(ch, ());
+    /// }
+    /// let rest = tree.split_at_nth(2);
+    /// assert_eq!(tree.iter().map(|(k, _)| *k).collect::<String>(), "eq");
+    /// assert_eq!(rest.iter().map(|(k, _)| *k).collect::<String>(), "rtwy");
+    /// ```
+    ///
+    pub fn split_at_nth(&mut self, index: usize) -> Tree<K, V>
     {
-        let mut t = self;
-        while let Filled(_) = t.left {
-            t = &t.left;
-        }
-        (t.key.clone(), t.value.clone())
-    }
-}
-
-impl<K, V> Default for Tree<K, V>
-{
-    /// Implements the default value for `Tree`. This is needed as part of the
-    /// `.take()` feature.
-    /// 
-    fn default() -> Self { 
-        Empty
+        let root          = self.root.take_tree();
+        let (left, right) = root.split_nth_internal(index, &mut self.stats);
+        self.root = left;
+        Tree::from_root(right)
     }
-}
-
-impl<K, V> Deref for Tree<K, V>
-where
-    K: Clone + Ord,
-    V: Clone,
-{
-    type Target = Node<K, V>;
-
-    /// Implements `Deref` for the `Tree`. This makes the fields of the `Node`
-    /// contained in the `Filled` variant accessible with minimal syntax.
-    /// 
-    fn deref(&self) -> &Self::Target {
-        match self {
-            Filled(node) => node,
-            Empty => panic!("Attempt to dereference an Empty Tree."),
-        }
-    }
-}
 
-impl<K, V> DerefMut for Tree<K, V>
-where
-    K: Clone + Ord,
-    V: Clone,
-{
-    /// Complements the implementation of `Deref` by giving access to mutable
-    /// `Node` fields with minimal syntax.
-    /// 
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        match self {
-            Filled(node) => node,
-            Empty => panic!("Attempt to dereference an Empty Tree."),
+    /// Returns the entry with the largest key that's less than or equal to
+    /// `key`, or `None` if there isn't one.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(10, 'a'), (20, 'b'), (30, 'c')]);
+    /// assert_eq!(tree.floor(&25), Some((&20, &'b')));
+    /// assert_eq!(tree.floor(&20), Some((&20, &'b')));
+    /// assert_eq!(tree.floor(&5),  None);
+    /// ```
+    ///
+    pub fn floor(&self, key: &K) -> Option<(&K, &V)>
+    {
+        use Ordering::*;
+        let mut ret = None;
+        let mut t   = &self.root;
+        while let Filled(node) = t {
+            match key.cmp(&node.key) {
+                Less => {
+                    t = &node.left;
+                },
+                Greater => {
+                    ret = Some((&node.key, &node.value));
+                    t   = &node.right;
+                },
+                Equal => {
+                    return Some((&node.key, &node.value));
+                },
+            }
         }
+        ret
     }
-}
-
-impl<K, V> Index<&K> for Tree<K, V>
-where
-    K: Clone + Ord,
-    V: Clone,
-{
-    type Output = V;
 
-    /// Gives the tree the square bracket indexing feature. The tree keys are
-    /// used to index their related values.
+    /// Returns the entry with the smallest key that's greater than or equal to
+    /// `key`, or `None` if there isn't one.
     /// ```
     /// use avl_tree::*;
-    /// let tree = Tree::new_with_insert("foo", 1);
-    /// assert_eq!(tree[&"foo"], 1);
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(10, 'a'), (20, 'b'), (30, 'c')]);
+    /// assert_eq!(tree.ceiling(&25), Some((&30, &'c')));
+    /// assert_eq!(tree.ceiling(&20), Some((&20, &'b')));
+    /// assert_eq!(tree.ceiling(&35), None);
     /// ```
     ///
-    fn index(&self, key: &K) -> &Self::Output
+    pub fn ceiling(&self, key: &K) -> Option<(&K, &V)>
     {
-        match self.get(key) {
-            Some(v) => v,
-            None => panic!("Attempt to read non-existent key."),
+        use Ordering::*;
+        let mut ret = None;
+        let mut t   = &self.root;
+        while let Filled(node) = t {
+            match key.cmp(&node.key) {
+                Less => {
+                    ret = Some((&node.key, &node.value));
+                    t   = &node.left;
+                },
+                Greater => {
+                    t = &node.right;
+                },
+                Equal => {
+                    return Some((&node.key, &node.value));
+                },
+            }
         }
+        ret
     }
-}
 
-impl<K, V> IndexMut<&K> for Tree<K, V>
-where
-    K: Clone + Ord,
-    V: Clone,
-{
-    /// Gives the tree the indexing feature so it behaves like a dictionary
-    /// which supports square bracket indexing.
+    /// Returns the entry whose key is closest to `key`, as measured by the
+    /// `dist` closure, which is given the query key and a candidate key. Only
+    /// the floor and the ceiling of `key` are candidates, so `dist` should
+    /// grow as keys get farther apart in the ordering. If both are the same
+    /// distance away, the floor is returned. Returns `None` only if the tree
+    /// is empty.
     /// ```
     /// use avl_tree::*;
-    /// let mut tree = Tree::new_with_insert("foo", 1);
-    /// tree[&"foo"] += 1;
-    /// assert_eq!(tree[&"foo"], 2);
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(10, 'a'), (20, 'b'), (30, 'c')]);
+    /// let dist = |a: &i32, b: &i32| (a - b).abs();
+    ///
+    /// assert_eq!(tree.nearest(&24, dist), Some((&20, &'b')));
+    /// assert_eq!(tree.nearest(&26, dist), Some((&30, &'c')));
+    /// assert_eq!(tree.nearest(&99, dist), Some((&30, &'c')));
     /// ```
-    /// 
-    fn index_mut(&mut self, key: &K) -> &mut Self::Output
+    ///
+    pub fn nearest<D, F>(&self, key: &K, dist: F) -> Option<(&K, &V)>
+    where
+        D: Ord,
+        F: Fn(&K, &K) -> D,
     {
-        match self.get_mut(key) {
-            Some(v) => v,
-            None => panic!("Key is not in the tree."),
+        match (self.floor(key), self.ceiling(key)) {
+            (Some(lo), Some(hi)) => {
+                if dist(key, hi.0) < dist(key, lo.0) { 
+                    Some(hi) 
+                } else { 
+                    Some(lo) 
+                }
+            },
+            (lo, hi) => lo.or(hi),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::*;
+    /// Returns the `Entry` for the given key, which can be used to inspect,
+    /// update, or insert the value for the key in place. This works like the
+    /// entry API of `BTreeMap`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut counts = Tree::new();
+    /// for ch in "hello".chars() {
+    ///     *counts.entry(ch).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(counts[&'l'], 2);
+    /// assert_eq!(counts[&'h'], 1);
+    /// ```
+    ///
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V>
+    {
+        if self.get(&key).is_some() {
+            let value = self.get_mut(&key).unwrap();
+            Entry::Occupied(OccupiedEntry { key, value })
+        } else {
+            Entry::Vacant(VacantEntry { tree: self, key })
+        }
+    }
 
-    #[test]
-    fn it_works() {
-        let mut tree = Tree::new();
-        let     data = "qwertyuiopasdfghjklzxcvbnmklasjfal;jasjfsa;";
-        for (i, ch) in data.chars().enumerate() {
-            tree.insert(ch, i);
+    /// Returns the `EntryRef` for a borrowed form of a key, such as a `&str`
+    /// for a tree with `String` keys. Unlike `.entry()`, no owned key is
+    /// needed up front; the key is only converted with `.to_owned()` when a
+    /// value is inserted into a vacant entry.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut counts: Tree<String, usize> = Tree::new();
+    /// for word in "a b a".split(' ') {
+    ///     *counts.entry_ref(word).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(counts[&"a".to_string()], 2);
+    /// ```
+    ///
+    pub fn entry_ref<'a, 'b, Q>(&'a mut self, key: &'b Q)
+        -> EntryRef<'a, 'b, K, Q, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ToOwned<Owned = K> + ?Sized,
+    {
+        if self.root.get_key_value_mut_by(key).is_some() {
+            let (key, value) = self.root.get_key_value_mut_by(key).unwrap();
+            EntryRef::Occupied(OccupiedEntryRef { key, value })
+        } else {
+            EntryRef::Vacant(VacantEntryRef { tree: self, key })
         }
-        assert_eq!(tree[&'q'], 0_usize);
-        assert_eq!(tree.get(&'w'), Some(&1_usize));
     }
-    
-    #[test]
-    fn update_or_insert_and_update() {
-        let mut tree = Tree::new();
 
-        match tree.get_mut(&'b') {
-            Some(value) => *value += 7,
+    /// Returns an iterator over the keys, in ascending order, whose entries
+    /// satisfy the predicate `pred`. The keys are filtered lazily as the tree
+    /// is walked, so no collection is built.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[('a', 5), ('b', 1), ('c', 9)]);
+    /// let big  = tree.keys_where(|_, v| *v > 3).collect::<String>();
+    /// assert_eq!(big, "ac");
+    /// ```
+    ///
+    pub fn keys_where<'a, F>(&'a self, pred: F) -> impl Iterator<Item = &'a K>
+    where
+        F: Fn(&K, &V) -> bool + 'a,
+    {
+        self.iter().filter(move |(k, v)| pred(k, v)).map(|(k, _)| k)
+    }
+
+    /// Collapses each run of adjacent entries (in key order) that have equal
+    /// values into a single entry. The key of the surviving entry is chosen by
+    /// folding `merge_key` over the keys of the run, from left to right. To
+    /// keep the tree ordered, `merge_key` must return a key that's within the
+    /// range of the two keys it's given, such as either one of them. The tree
+    /// is rebuilt in balance afterward, making this an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for (k, v) in [(1, 'a'), (2, 'a'), (3, 'b'), (4, 'a')] {
+    ///     tree.insert(k, v);
+    /// }
+    /// tree.coalesce(|first, _| *first);
+    ///
+    /// let items = tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+    /// assert_eq!(items, vec![(1, 'a'), (3, 'b'), (4, 'a')]);
+    /// ```
+    ///
+    pub fn coalesce<F>(&mut self, merge_key: F)
+    where
+        F: Fn(&K, &K) -> K,
+        V: PartialEq,
+    {
+        let mut items = Vec::with_capacity(self.len());
+        self.root.take_tree().drain_into(&mut items);
+
+        let mut merged: Vec<(K, V)> = Vec::with_capacity(items.len());
+        for (key, value) in items {
+            match merged.last_mut() {
+                Some((k, v)) if *v == value => {
+                    *k = merge_key(k, &key);
+                },
+                _ => {
+                    merged.push((key, value));
+                },
+            }
+        }
+        self.root = Link::from_sorted_vec(merged);
+    }
+
+    /// Returns an iterator over the entries of the tree in ascending order,
+    /// starting from `start` if it's present, or from the smallest key greater
+    /// than `start` otherwise. The iterator is positioned in `O(log n)` time.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (3, 'c'), (5, 'e')]);
+    /// let keys = tree.iter_from(&2).map(|(k, _)| *k).collect::<Vec<_>>();
+    /// assert_eq!(keys, vec![3, 5]);
+    /// ```
+    ///
+    pub fn iter_from(&self, start: &K) -> impl Iterator<Item = (&K, &V)>
+    {
+        Iter::new_at_key(&self.root, start)
+    }
+
+    /// Returns `true` if any entry in the tree holds a value equal to `value`.
+    /// Values aren't ordered within the tree, so this is a linear scan over
+    /// every entry, making it an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// assert!(tree.contains_value(&'b'));
+    /// assert!(!tree.contains_value(&'z'));
+    /// ```
+    ///
+    pub fn contains_value(&self, value: &V) -> bool
+    where
+        V: PartialEq,
+    {
+        self.find_key(value).is_some()
+    }
+
+    /// Returns the smallest key whose value is equal to `value`, or `None` if
+    /// no entry holds that value. Like `.contains_value()`, this walks the
+    /// entries in order and is an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'b')]);
+    /// assert_eq!(tree.find_key(&'b'), Some(&2));
+    /// assert_eq!(tree.find_key(&'z'), None);
+    /// ```
+    ///
+    pub fn find_key(&self, value: &V) -> Option<&K>
+    where
+        V: PartialEq,
+    {
+        self.iter().find(|(_, v)| *v == value).map(|(k, _)| k)
+    }
+
+    /// Returns the balance factor of the node holding `key`, which is the
+    /// height of its left sub-tree minus the height of its right sub-tree, or
+    /// `None` if the key isn't present. This is meant for inspecting the shape
+    /// of the tree when debugging or learning how the rotations work.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// assert_eq!(tree.balance_factor(&2), Some(0));
+    /// assert_eq!(tree.balance_factor(&4), None);
+    /// ```
+    ///
+    pub fn balance_factor(&self, key: &K) -> Option<isize>
+    {
+        use Ordering::*;
+        let mut tree = &self.root;
+        while let Filled(node) = tree {
+            match key.cmp(&node.key) {
+                Less    => tree = &node.left,
+                Greater => tree = &node.right,
+                Equal   => return Some(node.balance()),
+            }
+        }
+        None
+    }
+
+    /// Inserts a collection of unsorted entries in one pass. Rather than
+    /// rebalancing after each insertion, the new entries are gathered with the
+    /// existing ones, sorted once, and the tree is rebuilt in balance, making
+    /// this an `O((n + m) log(n + m))` operation. If a key occurs more than
+    /// once, the last value given for it wins.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.insert(2, 'x');
+    /// tree.bulk_insert(vec![(3, 'c'), (1, 'a'), (2, 'b')]);
+    ///
+    /// let items = tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+    /// assert_eq!(items, vec![(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// ```
+    ///
+    pub fn bulk_insert<I>(&mut self, items: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut all   = Vec::with_capacity(self.len());
+        self.root.take_tree().drain_into(&mut all);
+        all.extend(items);
+        all.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut merged: Vec<(K, V)> = Vec::with_capacity(all.len());
+        for (key, value) in all {
+            match merged.last_mut() {
+                Some((k, v)) if *k == key => {
+                    *v = value;
+                },
+                _ => {
+                    merged.push((key, value));
+                },
+            }
+        }
+        self.root = Link::from_sorted_vec(merged);
+    }
+
+    /// Returns the keys visited while searching for `key`, in order from the
+    /// root down to the node holding `key` itself, or `None` if the key isn't
+    /// present. This is useful for rendering or debugging the search path.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// assert_eq!(tree.get_path(&3), Some(vec![&2, &3]));
+    /// assert_eq!(tree.get_path(&4), None);
+    /// ```
+    ///
+    pub fn get_path(&self, key: &K) -> Option<Vec<&K>>
+    {
+        use Ordering::*;
+        let mut path = Vec::new();
+        let mut tree = &self.root;
+        while let Filled(node) = tree {
+            path.push(&node.key);
+            match key.cmp(&node.key) {
+                Less    => tree = &node.left,
+                Greater => tree = &node.right,
+                Equal   => return Some(path),
+            }
+        }
+        None
+    }
+
+    /// Gets the entry holding the smallest key that's greater than or equal to
+    /// `key` for in-place access. If there's no such key, the entry is vacant,
+    /// and inserting into it adds `key` to the tree. This is useful for filling
+    /// gaps in a sequence while updating the next entry when there is one.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new_with_insert(10, 1);
+    /// *tree.ceiling_entry(5).or_insert(0) += 1;
+    /// *tree.ceiling_entry(15).or_insert(0) += 1;
+    /// assert_eq!(tree[&10], 2);
+    /// assert_eq!(tree[&15], 1);
+    /// ```
+    ///
+    pub fn ceiling_entry(&mut self, key: K) -> CeilingEntry<'_, K, V>
+    {
+        // The search for the ceiling goes right until it reaches a key that's
+        // not less than `key`. If it runs off the end of the right spine,
+        // there's no ceiling; otherwise the search resumes from that node.
+        let mut steps = 0;
+        let mut t     = &self.root;
+        while let Filled(node) = t {
+            if key <= node.key {
+                break;
+            }
+            t      = &node.right;
+            steps += 1;
+        }
+        if !t.is_filled() {
+            return CeilingEntry::Vacant(VacantEntry { tree: self, key });
+        }
+        let mut t = &mut self.root;
+        for _ in 0..steps {
+            t = &mut t.right;
+        }
+        let (key, value) = t.ceiling_mut(&key).unwrap();
+        CeilingEntry::Occupied(OccupiedEntryRef { key, value })
+    }
+
+    /// Removes every entry from the tree, leaving it empty.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// tree.clear();
+    /// assert!(tree.is_empty());
+    /// ```
+    ///
+    pub fn clear(&mut self)
+    {
+        self.root  = Empty;
+        self.stats = Stats::default();
+    }
+
+    /// Returns the number of rotations performed to keep the tree balanced.
+    /// The count is kept by the tree itself rather than by its nodes, so this
+    /// is an `O(1)` operation, and it survives the tree being emptied by its
+    /// removals. It's only reset by `.clear()`. Requires the `stats` feature.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for i in 0..10 {
+    ///     tree.insert(i, ());
+    /// }
+    /// assert!(tree.rotation_count() > 0);
+    /// ```
+    ///
+    #[cfg(feature = "stats")]
+    pub fn rotation_count(&self) -> u64
+    {
+        self.stats.total
+    }
+
+    /// Returns the number of rotations performed since the tree was last
+    /// rebuilt by `.rebalance()`, or since it was created if it hasn't been.
+    /// This can drive a policy that rebalances the tree after a given amount
+    /// of rotation work. Like `.rotation_count()`, this requires the `stats`
+    /// feature.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// for i in 0..10 {
+    ///     tree.insert(i, ());
+    /// }
+    /// tree.rebalance();
+    /// assert_eq!(tree.rotations_since_rebalance(), 0);
+    /// assert!(tree.rotation_count() > 0);
+    /// ```
+    ///
+    #[cfg(feature = "stats")]
+    pub fn rotations_since_rebalance(&self) -> u64
+    {
+        self.stats.since_rebalance
+    }
+
+    /// Returns the entries with the smallest and the largest keys as a pair,
+    /// or `None` if the tree is empty. When the tree holds a single entry, both
+    /// halves of the pair refer to it. This descends each side of the tree
+    /// once, making it an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// assert_eq!(tree.min_max(), Some(((&1, &'a'), (&3, &'c'))));
+    /// ```
+    ///
+    #[allow(clippy::type_complexity)]
+    pub fn min_max(&self) -> Option<((&K, &V), (&K, &V))>
+    {
+        let first = self.first_key_value()?;
+        let last  = self.last_key_value()?;
+        Some((first, last))
+    }
+
+    /// Removes all but the `n` entries with the smallest keys. The tree is
+    /// split with `.split_at_nth()`, so what remains is left in balance and the
+    /// operation is `O(log n)` apart from dropping the removed entries. If `n`
+    /// is equal to or greater than the length of the tree, nothing is removed.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// tree.truncate_to_smallest(2);
+    /// assert_eq!(tree.iter().map(|(k, _)| *k).collect::<Vec<_>>(), [1, 2]);
+    /// ```
+    ///
+    pub fn truncate_to_smallest(&mut self, n: usize)
+    {
+        if n < self.len() {
+            self.split_at_nth(n);
+        }
+    }
+
+    /// Returns an iterator over mutable references to the values whose keys
+    /// fall within `range`, in ascending order by key. The iterator starts at
+    /// the beginning of the range in `O(log n)` time and stops at its end, so
+    /// the entries outside the range aren't visited.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 1), (2, 2), (3, 3)]);
+    /// for v in tree.range_values_mut(2..) {
+    ///     *v *= 10;
+    /// }
+    /// let values = tree.iter().map(|(_, v)| *v).collect::<Vec<_>>();
+    /// assert_eq!(values, [1, 20, 30]);
+    /// ```
+    ///
+    pub fn range_values_mut<R>(&mut self, range: R)
+        -> impl Iterator<Item = &mut V>
+    where
+        R: RangeBounds<K>,
+    {
+        let iter = IterMut::new_at_bound(&mut self.root, range.start_bound());
+        iter.take_while(move |(k, _)| before_end(range.end_bound(), k))
+            .map(|(_, v)| v)
+    }
+
+    /// Returns a pair holding the actual height of the tree and the optimal
+    /// height for a tree of its length, both counted as the number of edges on
+    /// the longest path from the root to a leaf. The optimal height is
+    /// `ceil(log2(len + 1)) - 1`. An empty tree reports `(0, 0)`. Comparing the
+    /// two can help decide whether a call to `.rebalance()` is worthwhile. The
+    /// actual height is found by visiting every node, making this `O(n)`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// assert_eq!(tree.height_report(), (1, 1));
+    /// ```
+    ///
+    pub fn height_report(&self) -> (usize, usize)
+    {
+        let len     = self.len();
+        let optimal = match len {
+            0 => 0,
+            _ => (usize::BITS - len.leading_zeros()) as usize - 1,
+        };
+        (self.root.edge_height(), optimal)
+    }
+
+    /// Inserts `value` for `key` only if the key is new, or if `value` is
+    /// greater than the value already held for it. Returns `true` if the tree
+    /// was changed, and `false` if the existing value was kept.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut scores = Tree::new();
+    /// assert!(scores.insert_max("ann", 10));
+    /// assert!(!scores.insert_max("ann", 7));
+    /// assert!(scores.insert_max("ann", 12));
+    /// assert_eq!(scores[&"ann"], 12);
+    /// ```
+    ///
+    pub fn insert_max(&mut self, key: K, value: V) -> bool
+    where
+        V: Ord,
+    {
+        match self.get_mut(&key) {
+            Some(v) if value > *v => {
+                *v = value;
+                true
+            },
+            Some(_) => {
+                false
+            },
             None => {
-                tree.insert('b', 7);
+                self.insert(key, value);
+                true
+            },
+        }
+    }
+
+    /// Returns an iterator over each pair of consecutive keys in ascending
+    /// order. This makes it easy to find gaps between keys, for instance.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree  = Tree::from_sorted_slice(&[(1, ()), (2, ()), (5, ())]);
+    /// let pairs = tree.key_pairs().collect::<Vec<_>>();
+    /// assert_eq!(pairs, [(&1, &2), (&2, &5)]);
+    /// ```
+    ///
+    pub fn key_pairs(&self) -> impl Iterator<Item = (&K, &K)>
+    {
+        let firsts  = self.iter().map(|(k, _)| k);
+        let seconds = self.iter().skip(1).map(|(k, _)| k);
+        firsts.zip(seconds)
+    }
+
+    /// Returns a deep copy of the sub-tree rooted at the node holding `key` as
+    /// an independent `Tree`, or `None` if the key isn't present. Since each
+    /// node's weight is the size of its own sub-tree, the copy is a valid tree
+    /// in its own right, and remains in balance.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// let sub  = tree.clone_subtree(&3).unwrap();
+    /// assert_eq!(sub.iter().collect::<Vec<_>>(), [(&3, &'c')]);
+    /// ```
+    ///
+    pub fn clone_subtree(&self, key: &K) -> Option<Tree<K, V>>
+    {
+        use Ordering::*;
+        let mut tree = &self.root;
+        while let Filled(node) = tree {
+            match key.cmp(&node.key) {
+                Less    => tree = &node.left,
+                Greater => tree = &node.right,
+                Equal   => return Some(Tree::from_root(tree.clone())),
             }
         }
-        assert_eq!(tree[&'b'], 7);
-        
-        match tree.get_mut(&'b') {
-            Some(value) => *value += 7,
-            None => {},
-        } 
-        assert_eq!(tree[&'b'], 14);
-        
-        tree[&'b'] += 7;
-        assert_eq!(tree[&'b'], 21);
+        None
+    }
+
+    /// Merges `other` into the tree, where all the keys of `other` are either
+    /// less than the smallest key of the tree, or greater than its largest key.
+    /// Which side `other` belongs on is detected automatically, after which the
+    /// two are joined by `.concat()`, attaching the shorter tree to the taller
+    /// one and rebalancing along the way, making this an `O(log n)` operation.
+    /// Debug builds panic if the key ranges overlap; release builds fall back
+    /// to inserting each entry.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(3, 'c'), (4, 'd')]);
+    /// tree.merge_sorted(Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]));
+    ///
+    /// let keys = tree.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+    /// assert_eq!(keys, [1, 2, 3, 4]);
+    /// ```
+    ///
+    pub fn merge_sorted(&mut self, mut other: Tree<K, V>)
+    {
+        let below = match (other.last_key_value(), self.first_key_value()) {
+            (Some((max, _)), Some((min, _))) => max < min,
+            _ => false,
+        };
+        if below {
+            std::mem::swap(self, &mut other);
+        }
+        self.concat(other);
+    }
+
+    /// Returns the entry holding the smallest value, or `None` if the tree is
+    /// empty. When several entries hold the smallest value, the one with the
+    /// smallest key is returned. Values aren't ordered within the tree, so
+    /// every entry is visited, making this an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[('a', 3), ('b', 1), ('c', 1)]);
+    /// assert_eq!(tree.min_by_value(), Some((&'b', &1)));
+    /// ```
+    ///
+    pub fn min_by_value(&self) -> Option<(&K, &V)>
+    where
+        V: Ord,
+    {
+        self.iter().reduce(|min, e| if e.1 < min.1 { e } else { min })
+    }
+
+    /// Returns the entry holding the largest value, or `None` if the tree is
+    /// empty. When several entries hold the largest value, the one with the
+    /// smallest key is returned. Like `.min_by_value()`, this is an `O(n)`
+    /// operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[('a', 1), ('b', 3), ('c', 3)]);
+    /// assert_eq!(tree.max_by_value(), Some((&'b', &3)));
+    /// ```
+    ///
+    pub fn max_by_value(&self) -> Option<(&K, &V)>
+    where
+        V: Ord,
+    {
+        self.iter().reduce(|max, e| if e.1 > max.1 { e } else { max })
+    }
+
+    /// Removes every entry for which `pred` returns `true`, and returns an
+    /// iterator over the removed entries in ascending order by key. The
+    /// entries are partitioned up front and the remaining ones are rebuilt
+    /// into a balanced tree, making this an `O(n)` operation, so the removal
+    /// happens whether or not the returned iterator is consumed.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// let odd = tree.extract_if(|k, _| k % 2 == 1).collect::<Vec<_>>();
+    /// assert_eq!(odd, [(1, 'a'), (3, 'c')]);
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    ///
+    pub fn extract_if<F>(&mut self, mut pred: F) -> impl Iterator<Item = (K, V)>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut items = Vec::with_capacity(self.len());
+        self.root.take_tree().drain_into(&mut items);
+        let (taken, kept): (Vec<_>, Vec<_>) =
+            items.into_iter().partition(|(k, v)| pred(k, v));
+        self.root = Link::from_sorted_vec(kept);
+        taken.into_iter()
+    }
+
+    /// Replaces the value of an existing key, moving `value` into the tree and
+    /// returning the former value as `Ok`. If the key isn't present, nothing is
+    /// inserted, and `value` is handed back as `Err`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new_with_insert("foo", 1);
+    /// assert_eq!(tree.replace(&"foo", 2), Ok(1));
+    /// assert_eq!(tree.replace(&"bar", 3), Err(3));
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    ///
+    pub fn replace(&mut self, key: &K, value: V) -> Result<V, V>
+    {
+        match self.get_mut(key) {
+            Some(v) => Ok(std::mem::replace(v, value)),
+            None => Err(value),
+        }
+    }
+
+    /// Returns the number of keys in the tree that are strictly less than
+    /// `key`. The weights of the left sub-trees passed over on the search path
+    /// are added up, so this is an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, ()), (3, ()), (5, ())]);
+    /// assert_eq!(tree.count_less(&3), 1);
+    /// assert_eq!(tree.count_less(&4), 2);
+    /// ```
+    ///
+    pub fn count_less(&self, key: &K) -> usize
+    {
+        let mut count = 0;
+        let mut t     = &self.root;
+        while let Filled(node) = t {
+            if node.key < *key {
+                count += 1 + node.left.weight_or_zero() as usize;
+                t      = &node.right;
+            } else {
+                t      = &node.left;
+            }
+        }
+        count
+    }
+
+    /// Returns the number of keys in the tree that are strictly greater than
+    /// `key`. Like `.count_less()`, this is an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, ()), (3, ()), (5, ())]);
+    /// assert_eq!(tree.count_greater(&3), 1);
+    /// assert_eq!(tree.count_greater(&0), 3);
+    /// ```
+    ///
+    pub fn count_greater(&self, key: &K) -> usize
+    {
+        let mut count = 0;
+        let mut t     = &self.root;
+        while let Filled(node) = t {
+            if node.key > *key {
+                count += 1 + node.right.weight_or_zero() as usize;
+                t      = &node.left;
+            } else {
+                t      = &node.right;
+            }
+        }
+        count
+    }
+
+    /// Consumes the tree and rebuilds it with its keys wrapped in `Reverse`,
+    /// so the new tree is ordered from the largest original key to the
+    /// smallest. The entries are moved into a balanced tree without any
+    /// rotations, making this an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    /// use std::cmp::Reverse;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// let rev  = tree.into_reversed();
+    /// assert_eq!(rev.first_key_value(), Some((&Reverse(2), &'b')));
+    /// ```
+    ///
+    pub fn into_reversed(self) -> Tree<Reverse<K>, V>
+    {
+        let mut items = self.into_iter()
+                            .map(|(k, v)| (Reverse(k), v))
+                            .collect::<Vec<_>>();
+        items.reverse();
+        Tree::from_root(Link::from_sorted_vec(items))
+    }
+
+    /// Returns up to `limit` keys in ascending order, starting with the key at
+    /// the ordinal position `offset`. Fewer keys are returned if the page runs
+    /// past the end of the tree, and none if `offset` is out of range. The
+    /// start of the page is found in `O(log n)` time, like `.get_nth()`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys("abcde".chars(), ());
+    /// assert_eq!(tree.keys_page(1, 2), [&'b', &'c']);
+    /// assert_eq!(tree.keys_page(4, 2), [&'e']);
+    /// ```
+    ///
+    pub fn keys_page(&self, offset: usize, limit: usize) -> Vec<&K>
+    {
+        Iter::new_at_nth(&self.root, offset)
+            .take(limit)
+            .map(|(k, _)| k)
+            .collect()
+    }
+
+    /// Applies a sequence of inserts and removals to the tree, in order, and
+    /// returns counts of the entries that were inserted, updated, and removed.
+    /// Each operation rebalances the tree as it would on its own.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new_with_insert(1, 'a');
+    /// let stats = tree.apply(vec![Op::Insert(1, 'b'), Op::Insert(2, 'c'),
+    ///                             Op::Remove(1), Op::Remove(9)]);
+    /// assert_eq!(stats, ApplyStats { inserted: 1, updated: 1, removed: 1 });
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    ///
+    pub fn apply<I>(&mut self, ops: I) -> ApplyStats
+    where
+        I: IntoIterator<Item = Op<K, V>>,
+    {
+        let mut stats = ApplyStats::default();
+        for op in ops {
+            match op {
+                Op::Insert(key, value) => {
+                    match self.insert(key, value) {
+                        Some(_) => stats.updated  += 1,
+                        None    => stats.inserted += 1,
+                    }
+                },
+                Op::Remove(key) => {
+                    if self.remove(&key).is_some() {
+                        stats.removed += 1;
+                    }
+                },
+            }
+        }
+        stats
+    }
+
+    /// Returns `true` if the stored weight of every node matches the actual
+    /// number of nodes in its sub-tree. The weights back the ordinal features,
+    /// such as `.get_nth()` and `.count_less()`, so this can be used to check
+    /// the tree's consistency in tests. The sizes are recounted bottom-up,
+    /// making this an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys(0..10, ());
+    /// assert!(tree.verify_weights());
+    /// ```
+    ///
+    pub fn verify_weights(&self) -> bool
+    {
+        self.root.verified_weight().is_some()
+    }
+
+    /// Returns a mutable reference to the value of `key`, first inserting the
+    /// value built by `f` if the key isn't present. If `f` fails, its error is
+    /// returned and the tree is left unchanged. The closure is only invoked if
+    /// the key is absent.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// let parsed = tree.get_or_try_insert_with("a", || "12".parse::<i32>());
+    /// assert_eq!(parsed, Ok(&mut 12));
+    ///
+    /// let failed = tree.get_or_try_insert_with("b", || "x".parse::<i32>());
+    /// assert!(failed.is_err());
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    ///
+    pub fn get_or_try_insert_with<E, F>(&mut self, key: K, f: F)
+        -> Result<&mut V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(f()?)),
+        }
+    }
+
+    /// Keeps only the `n` entries with the largest values, removing the rest.
+    /// Among entries with equal values, those with smaller keys are kept
+    /// first. The entries are ranked with a sort and the survivors rebuilt
+    /// into a balanced tree, making this an `O(n log n)` operation. If `n` is
+    /// equal to or greater than the length of the tree, nothing is removed.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[('a', 5), ('b', 9), ('c', 7)]);
+    /// tree.retain_top_n_by_value(2);
+    /// assert_eq!(tree.iter().map(|(k, _)| *k).collect::<String>(), "bc");
+    /// ```
+    ///
+    pub fn retain_top_n_by_value(&mut self, n: usize)
+    where
+        V: Ord,
+    {
+        if n >= self.len() {
+            return;
+        }
+        let mut items = Vec::with_capacity(self.len());
+        self.root.take_tree().drain_into(&mut items);
+
+        let mut ranks = (0..items.len()).collect::<Vec<_>>();
+        ranks.sort_by(|&a, &b| items[b].1.cmp(&items[a].1));
+
+        let mut keep = vec![false; items.len()];
+        for &i in &ranks[..n] {
+            keep[i] = true;
+        }
+        let mut keep = keep.into_iter();
+        items.retain(|_| keep.next().unwrap());
+        self.root = Link::from_sorted_vec(items);
+    }
+
+    /// Returns an iterator over the differences that would turn this tree into
+    /// `other`, in ascending order by key. Keys only in `other` are `Added`,
+    /// keys only in this tree are `Removed`, and keys in both with unequal
+    /// values are `Modified`. The trees are walked together with `.join()`,
+    /// making this an `O(n + m)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let old = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// let new = Tree::from_sorted_slice(&[(2, 'b'), (3, 'x'), (4, 'd')]);
+    ///
+    /// let changes = old.diff(&new).collect::<Vec<_>>();
+    /// assert_eq!(changes, [Change::Removed(&1, &'a'),
+    ///                      Change::Modified { key: &3, old: &'c', new: &'x' },
+    ///                      Change::Added(&4, &'d')]);
+    /// ```
+    ///
+    pub fn diff<'a>(&'a self, other: &'a Tree<K, V>)
+        -> impl Iterator<Item = Change<'a, K, V>>
+    where
+        V: PartialEq,
+    {
+        use JoinSide::*;
+        self.join(other).filter_map(|(key, side)| {
+            match side {
+                Left(v)  => Some(Change::Removed(key, v)),
+                Right(w) => Some(Change::Added(key, w)),
+                Both(v, w) if v != w => {
+                    Some(Change::Modified { key, old: v, new: w })
+                },
+                Both(..) => None,
+            }
+        })
+    }
+
+    /// Fills `buf` with the keys of the tree in ascending order, stopping when
+    /// either the keys run out or the buffer is full, and returns how many were
+    /// written. Slots past the returned count are left untouched. No memory is
+    /// allocated beyond the iterator's stack.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys(1..=5, ());
+    /// let mut buf = [None; 3];
+    /// assert_eq!(tree.collect_keys_into(&mut buf), 3);
+    /// assert_eq!(buf, [Some(&1), Some(&2), Some(&3)]);
+    /// ```
+    ///
+    pub fn collect_keys_into<'a>(&'a self, buf: &mut [Option<&'a K>]) -> usize
+    {
+        let mut count = 0;
+        for (slot, (key, _)) in buf.iter_mut().zip(self.iter()) {
+            *slot  = Some(key);
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns the entry at the ordinal position `n` counting back from the
+    /// end of the tree, where `0` is the entry with the largest key, or `None`
+    /// if `n` is out of range. Like `.get_nth()`, this is `O(log n)`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// assert_eq!(tree.get_nth_from_end(0), Some((&3, &'c')));
+    /// assert_eq!(tree.get_nth_from_end(2), Some((&1, &'a')));
+    /// assert_eq!(tree.get_nth_from_end(3), None);
+    /// ```
+    ///
+    pub fn get_nth_from_end(&self, n: usize) -> Option<(&K, &V)>
+    {
+        let index = self.len().checked_sub(n)?.checked_sub(1)?;
+        self.get_nth(index)
+    }
+
+    /// Returns `true` if no key in the tree falls within `range`. The search
+    /// descends toward the range, going right past keys below its start and
+    /// left past keys beyond its end, and stops at the first key inside it,
+    /// making this an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, ()), (5, ()), (9, ())]);
+    /// assert!(tree.range_is_empty(2..5));
+    /// assert!(!tree.range_is_empty(2..=5));
+    /// ```
+    ///
+    pub fn range_is_empty<R>(&self, range: R) -> bool
+    where
+        R: RangeBounds<K>,
+    {
+        let mut t = &self.root;
+        while let Filled(node) = t {
+            if !after_start(range.start_bound(), &node.key) {
+                t = &node.right;
+            } else if !before_end(range.end_bound(), &node.key) {
+                t = &node.left;
+            } else {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns the entry at the ordinal position `index` without checking that
+    /// the index is in range. This skips the `Option` handling of `.get_nth()`
+    /// for hot loops over positions that have already been validated. Debug
+    /// builds still panic on an out of range index.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be less than `self.len()`. Calling this with an index that
+    /// is out of range is undefined behavior.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// for i in 0..tree.len() {
+    ///     // SAFETY: `i` is less than the length of the tree.
+    ///     let (k, _) = unsafe { tree.get_unchecked_nth(i) };
+    ///     assert_eq!(*k, i + 1);
+    /// }
+    /// ```
+    ///
+    pub unsafe fn get_unchecked_nth(&self, mut index: usize) -> (&K, &V)
+    {
+        debug_assert!(index < self.len(), "Index is out of range.");
+        let mut t = &self.root;
+        loop {
+            let node = match t {
+                Filled(node) => node,
+                Empty => std::hint::unreachable_unchecked(),
+            };
+            let wt_l = node.left.weight_or_zero() as usize;
+            if index < wt_l {
+                t = &node.left;
+            } else if index == wt_l {
+                return (&node.key, &node.value);
+            } else {
+                index -= wt_l + 1;
+                t      = &node.right;
+            }
+        }
+    }
+
+    /// Returns the number of nodes with a balance factor of `-1`, `0`, and `1`,
+    /// in that order, or `None` if any node's balance factor falls outside that
+    /// range. The counts sum to the length of the tree. This is meant as a
+    /// metric of the tree's shape under a workload, and every node is visited,
+    /// making it an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys(0..3, ());
+    /// assert_eq!(tree.balance_histogram(), Some([0, 3, 0]));
+    /// ```
+    ///
+    pub fn balance_histogram(&self) -> Option<[usize; 3]>
+    {
+        let mut counts = [0; 3];
+        let mut stack  = vec![&self.root];
+        while let Some(t) = stack.pop() {
+            if let Filled(node) = t {
+                match node.balance() {
+                    bf @ -1..=1 => counts[(bf + 1) as usize] += 1,
+                    _ => return None,
+                }
+                stack.push(&node.left);
+                stack.push(&node.right);
+            }
+        }
+        Some(counts)
+    }
+
+    /// Returns mutable references to the values of two different keys, or
+    /// `None` if the keys are equal or either one is missing. This makes it
+    /// possible to combine or swap two values in place.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[('a', 1), ('b', 2)]);
+    /// if let Some((a, b)) = tree.get_pair_mut(&'a', &'b') {
+    ///     *a += *b;
+    /// }
+    /// assert_eq!(tree[&'a'], 3);
+    /// assert!(tree.get_pair_mut(&'a', &'a').is_none());
+    /// ```
+    ///
+    pub fn get_pair_mut(&mut self, a: &K, b: &K) -> Option<(&mut V, &mut V)>
+    {
+        self.root.get_two_mut(a, b)
+    }
+
+    /// Consumes the tree, passing each entry to `f` in ascending order by key,
+    /// and returns a new tree of the values `f` maps to. Entries for which `f`
+    /// returns `None` are left out. The output is built in balance without any
+    /// rotations, making this an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, "7"), (2, "x"), (3, "9")]);
+    /// let nums = tree.filter_map_values(|_, v| v.parse::<u8>().ok());
+    /// assert_eq!(nums.iter().collect::<Vec<_>>(), [(&1, &7), (&3, &9)]);
+    /// ```
+    ///
+    pub fn filter_map_values<W, F>(self, mut f: F) -> Tree<K, W>
+    where
+        W: Clone,
+        F: FnMut(&K, V) -> Option<W>,
+    {
+        let items = self.into_iter()
+                        .filter_map(|(k, v)| f(&k, v).map(|w| (k, w)))
+                        .collect::<Vec<_>>();
+        Tree::from_root(Link::from_sorted_vec(items))
+    }
+
+    /// Splits the tree where `pred` first turns `false`, keeping the initial
+    /// run of keys that satisfy `pred` and returning a tree of the rest. The
+    /// predicate must be monotonic over the keys in ascending order: `true` for
+    /// a prefix of them, then `false` for the remainder. The split point is
+    /// found by a binary search down the tree, and the split itself is done by
+    /// `.split_at_nth()`, making this an `O(log n)` operation. If `pred` isn't
+    /// monotonic, the split point is unspecified.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::with_sorted_keys(0..5, ());
+    /// let rest = tree.split_while(|k| *k < 2);
+    /// assert_eq!(tree.iter().map(|(k, _)| *k).collect::<Vec<_>>(), [0, 1]);
+    /// assert_eq!(rest.iter().map(|(k, _)| *k).collect::<Vec<_>>(), [2, 3, 4]);
+    /// ```
+    ///
+    pub fn split_while<F>(&mut self, pred: F) -> Tree<K, V>
+    where
+        F: Fn(&K) -> bool,
+    {
+        let mut index = 0;
+        let mut t     = &self.root;
+        while let Filled(node) = t {
+            if pred(&node.key) {
+                index += 1 + node.left.weight_or_zero() as usize;
+                t      = &node.right;
+            } else {
+                t      = &node.left;
+            }
+        }
+        self.split_at_nth(index)
+    }
+
+    /// Inserts the key and value like `.insert()`, but if the key is already
+    /// present, first calls `on_overwrite` with the key, the old value, and the
+    /// new value. Returns the old value, if there was one. The callback isn't
+    /// invoked when the key is new to the tree.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new_with_insert("foo", 1);
+    /// let mut log  = vec![];
+    /// tree.insert_observed("foo", 2, |k, old, new| {
+    ///     log.push(format!("{}: {} -> {}", k, old, new));
+    /// });
+    /// assert_eq!(log, ["foo: 1 -> 2"]);
+    /// ```
+    ///
+    pub fn insert_observed<F>(&mut self, key: K, value: V, on_overwrite: F)
+        -> Option<V>
+    where
+        F: FnOnce(&K, &V, &V),
+    {
+        match self.get_mut(&key) {
+            Some(old) => {
+                on_overwrite(&key, old, &value);
+                Some(std::mem::replace(old, value))
+            },
+            None => {
+                self.insert(key, value);
+                None
+            },
+        }
+    }
+
+    /// Returns the height of the sub-tree rooted at the node holding `key`,
+    /// counted as the number of edges on the longest path down to a leaf, so a
+    /// leaf's height is `0`. Returns `None` if the key isn't present. The
+    /// sub-tree is measured by visiting each of its nodes.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys(1..=3, ());
+    /// assert_eq!(tree.subtree_height(&2), Some(1));
+    /// assert_eq!(tree.subtree_height(&3), Some(0));
+    /// assert_eq!(tree.subtree_height(&4), None);
+    /// ```
+    ///
+    pub fn subtree_height(&self, key: &K) -> Option<usize>
+    {
+        use Ordering::*;
+        let mut t = &self.root;
+        while let Filled(node) = t {
+            match key.cmp(&node.key) {
+                Less    => t = &node.left,
+                Greater => t = &node.right,
+                Equal   => return Some(t.edge_height()),
+            }
+        }
+        None
+    }
+
+    /// Moves every key/value pair out of the tree into a `Vec` sorted by key,
+    /// leaving the tree empty. Nothing is cloned, so the pairs can be reused
+    /// elsewhere. This is an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// assert_eq!(tree.take_all(), [(1, 'a'), (2, 'b')]);
+    /// assert!(tree.is_empty());
+    /// ```
+    ///
+    pub fn take_all(&mut self) -> Vec<(K, V)>
+    {
+        let mut items = Vec::with_capacity(self.len());
+        self.root.take_tree().drain_into(&mut items);
+        items
+    }
+
+    /// Returns the number of distinct values held in the tree. The values are
+    /// cloned into a temporary `Vec`, which is sorted to count the unique ones,
+    /// making this an `O(n log n)` operation that allocates `O(n)` space.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'x'), (2, 'y'), (3, 'x')]);
+    /// assert_eq!(tree.distinct_value_count(), 2);
+    /// ```
+    ///
+    pub fn distinct_value_count(&self) -> usize
+    where
+        V: Ord,
+    {
+        let mut values = self.iter()
+                             .map(|(_, v)| v.clone())
+                             .collect::<Vec<_>>();
+        values.sort_unstable();
+        values.dedup();
+        values.len()
+    }
+
+    /// Returns the entry with the smallest key within `range`, or `None` if no
+    /// key falls within it. This is the ceiling of the range's lower bound,
+    /// provided it's within the upper bound, and is found in `O(log n)` time.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (5, 'e'), (9, 'i')]);
+    /// assert_eq!(tree.range_first(2..), Some((&5, &'e')));
+    /// assert_eq!(tree.range_first(2..5), None);
+    /// ```
+    ///
+    pub fn range_first<R>(&self, range: R) -> Option<(&K, &V)>
+    where
+        R: RangeBounds<K>,
+    {
+        let mut ret = None;
+        let mut t   = &self.root;
+        while let Filled(node) = t {
+            if after_start(range.start_bound(), &node.key) {
+                ret = Some((&node.key, &node.value));
+                t   = &node.left;
+            } else {
+                t   = &node.right;
+            }
+        }
+        ret.filter(|(k, _)| before_end(range.end_bound(), k))
+    }
+
+    /// Returns the entry with the largest key within `range`, or `None` if no
+    /// key falls within it. This is the floor of the range's upper bound,
+    /// provided it's within the lower bound, and is found in `O(log n)` time.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (5, 'e'), (9, 'i')]);
+    /// assert_eq!(tree.range_last(..9), Some((&5, &'e')));
+    /// assert_eq!(tree.range_last(6..9), None);
+    /// ```
+    ///
+    pub fn range_last<R>(&self, range: R) -> Option<(&K, &V)>
+    where
+        R: RangeBounds<K>,
+    {
+        let mut ret = None;
+        let mut t   = &self.root;
+        while let Filled(node) = t {
+            if before_end(range.end_bound(), &node.key) {
+                ret = Some((&node.key, &node.value));
+                t   = &node.right;
+            } else {
+                t   = &node.left;
+            }
+        }
+        ret.filter(|(k, _)| after_start(range.start_bound(), k))
+    }
+
+    /// Returns a lazy iterator over the union of this tree and `other` in
+    /// ascending key order. Where both trees hold the same key, the entry from
+    /// `self` is yielded and the one from `other` is skipped. No combined tree
+    /// is built; the walk is `O(n + m)` and only holds the two iterator stacks.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let t1 = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// let t2 = Tree::from_sorted_slice(&[(2, 'x'), (3, 'c')]);
+    ///
+    /// let merged = t1.merged(&t2).collect::<Vec<_>>();
+    /// assert_eq!(merged, [(&1, &'a'), (&2, &'b'), (&3, &'c')]);
+    /// ```
+    ///
+    pub fn merged<'a>(&'a self, other: &'a Tree<K, V>)
+        -> impl Iterator<Item = (&'a K, &'a V)>
+    {
+        use JoinSide::*;
+        self.join(other).map(|(key, side)| {
+            match side {
+                Left(v) | Both(v, _) => (key, v),
+                Right(w)             => (key, w),
+            }
+        })
+    }
+
+    /// Returns `true` if any entry with a key within `range` satisfies `pred`.
+    /// The entries are visited in ascending key order starting from the lower
+    /// bound, and the walk stops at the first match or at the upper bound, so
+    /// nothing outside the range is visited and nothing is collected.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 10), (2, 25), (3, 30)]);
+    /// assert!(tree.any_in_range(2.., |_, v| v % 5 == 0));
+    /// assert!(!tree.any_in_range(..3, |_, v| *v > 25));
+    /// ```
+    ///
+    pub fn any_in_range<R, F>(&self, range: R, mut pred: F) -> bool
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&K, &V) -> bool,
+    {
+        Iter::new_at_bound(&self.root, range.start_bound())
+            .take_while(|(k, _)| before_end(range.end_bound(), k))
+            .any(|(k, v)| pred(k, v))
+    }
+
+    /// Returns the `n`th smallest value across all the entries, counting from
+    /// zero, or `None` if `n` is out of range. Values are ordered by value, not
+    /// by key, so this allocates a temporary `Vec` of references to every value
+    /// and selects from it with `select_nth_unstable()`, which is `O(n)`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 30), (2, 10), (3, 20)]);
+    /// assert_eq!(tree.nth_value(0), Some(&10));
+    /// assert_eq!(tree.nth_value(2), Some(&30));
+    /// assert_eq!(tree.nth_value(3), None);
+    /// ```
+    ///
+    pub fn nth_value(&self, n: usize) -> Option<&V>
+    where
+        V: Ord,
+    {
+        if n >= self.len() {
+            return None;
+        }
+        let mut values = self.iter().map(|(_, v)| v).collect::<Vec<_>>();
+        Some(*values.select_nth_unstable(n).1)
+    }
+
+    /// Returns the ordinal position of the floor of `key`, which is the entry
+    /// with the largest key that's less than or equal to `key`, or `None` if
+    /// there isn't one. The keys at or below `key` are counted along the
+    /// search path as in `.count_less()`, so this is an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(10, ()), (20, ()), (30, ())]);
+    /// assert_eq!(tree.floor_rank(&25), Some(1));
+    /// assert_eq!(tree.floor_rank(&30), Some(2));
+    /// assert_eq!(tree.floor_rank(&5), None);
+    /// ```
+    ///
+    pub fn floor_rank(&self, key: &K) -> Option<usize>
+    {
+        let mut count = 0usize;
+        let mut t     = &self.root;
+        while let Filled(node) = t {
+            if node.key <= *key {
+                count += 1 + node.left.weight_or_zero() as usize;
+                t      = &node.right;
+            } else {
+                t      = &node.left;
+            }
+        }
+        count.checked_sub(1)
+    }
+
+    /// Returns the ordinal position of the ceiling of `key`, which is the
+    /// entry with the smallest key that's greater than or equal to `key`, or
+    /// `None` if there isn't one. The rank is the number of keys less than
+    /// `key`, so this is an `O(log n)` operation. The number of keys within
+    /// `lo..=hi` is the difference of `floor_rank(hi)` and `ceiling_rank(lo)`,
+    /// plus one, when both exist and the first isn't less than the second.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(10, ()), (20, ()), (30, ())]);
+    /// assert_eq!(tree.ceiling_rank(&15), Some(1));
+    /// assert_eq!(tree.ceiling_rank(&10), Some(0));
+    /// assert_eq!(tree.ceiling_rank(&35), None);
+    /// ```
+    ///
+    pub fn ceiling_rank(&self, key: &K) -> Option<usize>
+    {
+        Some(self.count_less(key)).filter(|&rank| rank < self.len())
+    }
+
+    /// Removes every entry whose key isn't also a key of `keys`, whatever the
+    /// value type of `keys` is. The entries are moved out in order and walked
+    /// together with the keys of `keys`, then the survivors are rebuilt into a
+    /// balanced tree, making this an `O(n + m)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// let mask = Tree::from_sorted_slice(&[(1, "x"), (3, "y"), (4, "z")]);
+    /// tree.retain_keys_in(&mask);
+    /// assert_eq!(tree.iter().collect::<Vec<_>>(), [(&1, &'a'), (&3, &'c')]);
+    /// ```
+    ///
+    pub fn retain_keys_in<W>(&mut self, keys: &Tree<K, W>)
+    {
+        let mut others = Iter::new(&keys.root).map(|(k, _)| k).peekable();
+        let mut items  = Vec::with_capacity(self.len());
+        self.root.take_tree().drain_into(&mut items);
+        items.retain(|(k, _)| {
+            while others.next_if(|other| *other < k).is_some() {}
+            others.peek() == Some(&k)
+        });
+        self.root = Link::from_sorted_vec(items);
+    }
+
+    /// Removes the entry at the ordinal position `index` like `.pop_nth()`,
+    /// returning the removed key and value along with the length of the tree
+    /// after the removal. If `index` is out of range, `None` is returned and
+    /// the tree is left unchanged. This is an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// assert_eq!(tree.remove_nth_reporting(1), Some(((2, 'b'), 2)));
+    /// assert_eq!(tree.remove_nth_reporting(2), None);
+    /// ```
+    ///
+    pub fn remove_nth_reporting(&mut self, index: usize)
+        -> Option<((K, V), usize)>
+    {
+        let pair = self.pop_nth(index)?;
+        Some((pair, self.len()))
+    }
+
+    /// Compares the contents of two trees while skipping the keys listed in
+    /// `ignore`. The trees are equal if every other key is in both of them
+    /// with equal values. The trees are walked together with `.join()`, and
+    /// `ignore` is only searched for the entries that differ, so this is
+    /// `O(n + m)` plus a linear scan of `ignore` per difference.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let a = Tree::from_sorted_slice(&[("id", 1), ("time", 100)]);
+    /// let b = Tree::from_sorted_slice(&[("id", 1), ("time", 250)]);
+    /// assert!(a.eq_ignoring(&b, &["time"]));
+    /// assert!(!a.eq_ignoring(&b, &[]));
+    /// ```
+    ///
+    pub fn eq_ignoring(&self, other: &Tree<K, V>, ignore: &[K]) -> bool
+    where
+        V: PartialEq,
+    {
+        use JoinSide::*;
+        self.join(other).all(|(key, side)| {
+            match side {
+                Both(v, w) if v == w => true,
+                _ => ignore.contains(key),
+            }
+        })
+    }
+
+    /// Returns the entry just before `key`, the entry for `key` itself, and the
+    /// entry just after it, as `(predecessor, exact, successor)`. Each part is
+    /// `None` where there's no such entry, as for a key below the smallest or
+    /// above the largest. The neighbors are tracked along a single descent to
+    /// `key`; when it's found, they come from the far ends of its sub-trees if
+    /// it has any. This is an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(10, 'a'), (20, 'b'), (30, 'c')]);
+    /// assert_eq!(tree.neighbors(&20),
+    ///            (Some((&10, &'a')), Some((&20, &'b')), Some((&30, &'c'))));
+    /// assert_eq!(tree.neighbors(&25), (Some((&20, &'b')), None,
+    ///                                  Some((&30, &'c'))));
+    /// ```
+    ///
+    #[allow(clippy::type_complexity)]
+    pub fn neighbors(&self, key: &K)
+        -> (Option<(&K, &V)>, Option<(&K, &V)>, Option<(&K, &V)>)
+    {
+        use Ordering::*;
+        let mut prev = None;
+        let mut next = None;
+        let mut t    = &self.root;
+        while let Filled(node) = t {
+            match key.cmp(&node.key) {
+                Less => {
+                    next = Some((&node.key, &node.value));
+                    t    = &node.left;
+                },
+                Greater => {
+                    prev = Some((&node.key, &node.value));
+                    t    = &node.right;
+                },
+                Equal => {
+                    let prev = node.left.last_key_value().or(prev);
+                    let next = node.right.first_key_value().or(next);
+                    return (prev, Some((&node.key, &node.value)), next);
+                },
+            }
+        }
+        (prev, None, next)
+    }
+
+    /// Appends `other` to the tree, where every key of `other` is greater than
+    /// every key of the tree. The smallest entry of `other` is taken as the
+    /// pivot for an AVL join, which follows the taller tree's inner spine down
+    /// to where the heights match, attaches the shorter tree there, and
+    /// rebalances along the path. This is an `O(log n + log m)` operation.
+    /// Debug builds panic if the keys aren't ordered this way; release builds
+    /// fall back to inserting each entry of `other`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// tree.concat(Tree::from_sorted_slice(&[(3, 'c'), (4, 'd')]));
+    ///
+    /// let keys = tree.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+    /// assert_eq!(keys, [1, 2, 3, 4]);
+    /// ```
+    ///
+    pub fn concat(&mut self, mut other: Tree<K, V>)
+    {
+        let above = match (self.last_key_value(), other.first_key_value()) {
+            (Some((max, _)), Some((min, _))) => max < min,
+            _ => true,
+        };
+        debug_assert!(above, "The keys of `other` must be greater than the \
+                              tree's keys.");
+        if !above {
+            for (key, value) in other {
+                self.insert(key, value);
+            }
+        } else if !other.is_empty() {
+            let (key, value) = other.root.pop_min(&mut self.stats);
+            let left         = self.root.take_tree();
+            let stats        = &mut self.stats;
+            self.root = Link::join_pivot(left, key, value, other.root, stats);
+        }
+    }
+
+    /// Sorts the keys of the tree into buckets given by `classify`, returning
+    /// the number of keys in each bucket. Buckets that no key maps to are left
+    /// out. The counts are gathered in a single in-order walk with `.fold()`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys(["ant", "bee", "wasp"].iter(), ());
+    /// let sizes = tree.count_by(|k| k.len());
+    /// assert_eq!(sizes.into_iter().collect::<Vec<_>>(), [(3, 2), (4, 1)]);
+    /// ```
+    ///
+    pub fn count_by<B, F>(&self, mut classify: F) -> BTreeMap<B, usize>
+    where
+        B: Ord,
+        F: FnMut(&K) -> B,
+    {
+        self.fold(BTreeMap::new(), |mut counts, k, _| {
+            *counts.entry(classify(k)).or_insert(0) += 1;
+            counts
+        })
+    }
+
+    /// Returns an iterator over the entries of the tree in ascending order by
+    /// value, with entries of equal values in ascending order by key. The
+    /// entries are gathered in key order into a temporary `Vec` of references,
+    /// which is then stably sorted by value before the first one is yielded,
+    /// making this an `O(n log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[('a', 3), ('b', 1), ('c', 2)]);
+    /// let keys = tree.iter_by_value().map(|(k, _)| *k).collect::<String>();
+    /// assert_eq!(keys, "bca");
+    /// ```
+    ///
+    pub fn iter_by_value(&self) -> impl Iterator<Item = (&K, &V)>
+    where
+        V: Ord,
+    {
+        let mut entries = self.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(_, v)| *v);
+        entries.into_iter()
+    }
+
+    /// Renders the shape of the tree as a Graphviz DOT digraph. Each node is
+    /// declared with a label holding its key and balance factor, and has an
+    /// edge to each of its children labeled `L` or `R`. Empty children are
+    /// left out. The nodes are numbered in pre-order, so the root is `n0`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys(1..=3, ());
+    /// let dot  = tree.to_dot();
+    /// assert!(dot.starts_with("digraph Tree {"));
+    /// assert!(dot.contains("n0 [label=\"2\\nbf=0\"];"));
+    /// assert!(dot.contains("n0 -> n1 [label=\"L\"];"));
+    /// ```
+    ///
+    pub fn to_dot(&self) -> String
+    where
+        K: Display,
+    {
+        let mut dot   = String::from("digraph Tree {\n");
+        let mut stack = vec![(&self.root, None)];
+        let mut id    = 0;
+        while let Some((t, parent)) = stack.pop() {
+            if let Filled(node) = t {
+                let key = node.key.to_string()
+                                  .replace('\\', "\\\\")
+                                  .replace('"', "\\\"");
+                writeln!(dot, "    n{} [label=\"{}\\nbf={}\"];",
+                         id, key, node.balance()).unwrap();
+                if let Some((parent, side)) = parent {
+                    writeln!(dot, "    n{} -> n{} [label=\"{}\"];",
+                             parent, id, side).unwrap();
+                }
+                stack.push((&node.right, Some((id, "R"))));
+                stack.push((&node.left, Some((id, "L"))));
+                id += 1;
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Removes every entry whose key falls within `range`, and returns an
+    /// iterator that moves the removed entries out in ascending order by key.
+    /// The tree is split with `.split_while()` at both ends of the range and
+    /// the outer parts are joined back together with `.concat()`, so what
+    /// remains is balanced. The removal is `O(log n)` and happens whether or
+    /// not the returned iterator is consumed.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// let drained = tree.drain_range(2..).collect::<Vec<_>>();
+    /// assert_eq!(drained, [(2, 'b'), (3, 'c')]);
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    ///
+    pub fn drain_range<R>(&mut self, range: R) -> IntoIter<K, V>
+    where
+        R: RangeBounds<K>,
+    {
+        let     start  = range.start_bound();
+        let     end    = range.end_bound();
+        let mut inside = self.split_while(|k| !after_start(start, k));
+        let     above  = inside.split_while(|k| before_end(end, k));
+        self.concat(above);
+        inside.into_iter()
+    }
+
+    /// Returns the depths of the shallowest and the deepest leaves of the tree,
+    /// counted as the number of edges from the root, or `None` if the tree is
+    /// empty. A leaf is a node without children. In a balanced tree the two
+    /// stay close, the deepest leaf being at most about twice as deep as the
+    /// shallowest, so a wide gap points to a balancing bug. Every node is
+    /// visited, making this an `O(n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::with_sorted_keys(0..4, ());
+    /// assert_eq!(tree.leaf_depth_range(), Some((1, 2)));
+    /// assert_eq!(Tree::<i32, ()>::new().leaf_depth_range(), None);
+    /// ```
+    ///
+    pub fn leaf_depth_range(&self) -> Option<(usize, usize)>
+    {
+        self.root.leaf_depth_range()
+    }
+}
+
+impl<K, V> Link<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    /// Internal implementation for `.first_key_value()`. Returns the smallest
+    /// key of the sub-tree along with its value.
+    ///
+    fn first_key_value(&self) -> Option<(&K, &V)>
+    {
+        let mut t = self;
+        while let Filled(node) = t {
+            if !node.left.is_filled() {
+                return Some((&node.key, &node.value));
+            }
+            t = &node.left;
+        }
+        None
+    }
+
+    /// Internal implementation for `.last_key_value()`. Returns the largest
+    /// key of the sub-tree along with its value.
+    ///
+    fn last_key_value(&self) -> Option<(&K, &V)>
+    {
+        let mut t = self;
+        while let Filled(node) = t {
+            if !node.right.is_filled() {
+                return Some((&node.key, &node.value));
+            }
+            t = &node.right;
+        }
+        None
+    }
+
+    /// Internal implementation for `.leaf_depth_range()`, which recurses into
+    /// the sub-trees of each node.
+    ///
+    fn leaf_depth_range(&self) -> Option<(usize, usize)>
+    {
+        match self {
+            Filled(node) => {
+                let below = [&node.left, &node.right]
+                    .iter()
+                    .filter_map(|t| t.leaf_depth_range())
+                    .reduce(|(lo1, hi1), (lo2, hi2)| {
+                        (lo1.min(lo2), hi1.max(hi2))
+                    });
+                Some(below.map_or((0, 0), |(lo, hi)| (lo + 1, hi + 1)))
+            },
+            Empty => None,
+        }
+    }
+
+    /// Internal implementation for `.find_nth()`. The public facing version
+    /// prohibits passing negative values as indices, while the internal version
+    /// needs flexibility to avoid unsigned overflows.
+    /// 
+    fn get_nth_internal(&self, index: isize) -> Option<(&K, &V)>
+    {
+        use Ordering::*;
+        
+        let mut ret  = None;
+        let     wt_l = match &self.left { Filled(node) => node.weight, 
+                                          Empty        => 0,           };
+        let idx_adj = index - wt_l;
+        
+        match idx_adj.cmp(&0_isize) {
+            Equal => { 
+                ret = Some((&self.key, &self.value)); 
+            },
+            Greater => {
+                if self.right.is_filled() {
+                    ret = self.right.get_nth_internal(idx_adj - 1);
+                }
+            },
+            Less => {
+                if self.left.is_filled() {
+                    ret = self.left.get_nth_internal(index);
+                }
+            },
+        }
+        ret
+    }
+
+    /// Internal implementation for `.fold()`. Takes the closure by reference so
+    /// it can be passed down the recursion.
+    ///
+    fn fold_internal<B, F>(&self, acc: B, f: &mut F) -> B
+    where
+        F: FnMut(B, &K, &V) -> B,
+    {
+        match self {
+            Filled(node) => {
+                let acc = node.left.fold_internal(acc, f);
+                let acc = f(acc, &node.key, &node.value);
+                node.right.fold_internal(acc, f)
+            },
+            Empty => acc,
+        }
+    }
+
+    /// Internal implementation for `.get()`. Returns the value corresponding
+    /// to the given key. Doesn't check whether tree is empty.
+    ///
+    fn get_internal(&self, key: &K) -> Option<&V>
+    {
+        use Ordering::*;
+        let mut ret = None;
+        match key.cmp(&self.key) {
+            Less => {
+                if self.left.is_filled() {
+                    ret = self.left.get_internal(key);
+                }
+            },
+            Greater => {
+                if self.right.is_filled() {
+                    ret = self.right.get_internal(key);
+                }
+            },
+            Equal => {
+                ret = Some(&self.value)
+            },
+        }
+        ret
+    }
+
+    /// Returns a mutable reference to the value of the given key within the
+    /// sub-tree, or `None` if it's `Empty` or doesn't hold the key.
+    ///
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>
+    {
+        match self {
+            Filled(_) => self.get_mut_internal(key),
+            _ => None,
+        }
+    }
+
+    /// Internal implementation for `.get_mut()`. Returns a mutable reference
+    /// to the corresponding value of the key. Doesn't check whether tree is
+    /// empty or not before executing search.
+    ///
+    fn get_mut_internal(&mut self, key: &K) -> Option<&mut V>
+    {
+        use Ordering::*;
+        let mut ret = None;
+        match key.cmp(&self.key) {
+            Less => {
+                if self.left.is_filled() {
+                    ret = self.left.get_mut_internal(key);
+                }
+            },
+            Greater => {
+                if self.right.is_filled() {
+                    ret = self.right.get_mut_internal(key);
+                }
+            },
+            Equal => {
+                ret = Some(&mut self.value);
+            }
+        }
+        ret
+    }
+
+    /// Internal implementation for `.ceiling_entry()`. Returns the key and a
+    /// mutable reference to the value of the smallest key that's greater than
+    /// or equal to `key`. The node's fields are borrowed separately so that
+    /// the candidate can be kept while the search continues to its left.
+    ///
+    fn ceiling_mut(&mut self, key: &K) -> Option<(&K, &mut V)>
+    {
+        use Ordering::*;
+        let mut ret = None;
+        let mut t   = self;
+        while let Filled(node) = t {
+            let Node { key: k, value, left, right, .. } = &mut **node;
+            match key.cmp(k) {
+                Less => {
+                    ret = Some((&*k, value));
+                    t   = left;
+                },
+                Greater => {
+                    t = right;
+                },
+                Equal => {
+                    return Some((&*k, value));
+                },
+            }
+        }
+        ret
+    }
+
+    /// Looks up a key by a borrowed form of it, and returns references to the
+    /// key as it's held in the tree and to its value, or `None` if the key
+    /// isn't present.
+    ///
+    fn get_key_value_mut_by<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        use Ordering::*;
+        let mut t = self;
+        while let Filled(node) = t {
+            let Node { key: k, value, left, right, .. } = &mut **node;
+            match key.cmp((*k).borrow()) {
+                Less    => t = left,
+                Greater => t = right,
+                Equal   => return Some((k, value)),
+            }
+        }
+        None
+    }
+
+    /// Returns a mutable reference to the value of the entry at the ordinal
+    /// position `index`, or `None` if the index is out of range.
+    ///
+    fn get_nth_value_mut(&mut self, mut index: usize) -> Option<&mut V>
+    {
+        let mut t = self;
+        while let Filled(node) = t {
+            let Node { value, left, right, .. } = &mut **node;
+            let wt_l = left.weight_or_zero() as usize;
+            if index < wt_l {
+                t = left;
+            } else if index == wt_l {
+                return Some(value);
+            } else {
+                index -= wt_l + 1;
+                t      = right;
+            }
+        }
+        None
+    }
+
+    /// Returns mutable references to the values of two different keys. The
+    /// tree is descended until the paths to `a` and `b` diverge, then each key
+    /// is searched for in its own separate part of the tree, which keeps the
+    /// two references disjoint. Returns `None` if either key is missing. The
+    /// keys must not be equal.
+    ///
+    fn get_two_mut(&mut self, a: &K, b: &K) -> Option<(&mut V, &mut V)>
+    {
+        use Ordering::*;
+        let node = match self {
+            Filled(node) => node,
+            Empty => return None,
+        };
+        match (a.cmp(&node.key), b.cmp(&node.key)) {
+            (Less,    Less   ) => node.left.get_two_mut(a, b),
+            (Greater, Greater) => node.right.get_two_mut(a, b),
+            (ord_a,   ord_b  ) => {
+                let Node { value: v, left: l, right: r, .. } = &mut **node;
+                match (ord_a, ord_b) {
+                    (Less,    Equal  ) => Some((l.get_mut(a)?, v)),
+                    (Less,    Greater) => Some((l.get_mut(a)?, r.get_mut(b)?)),
+                    (Equal,   Less   ) => Some((v,             l.get_mut(b)?)),
+                    (Equal,   Greater) => Some((v,             r.get_mut(b)?)),
+                    (Greater, Less   ) => Some((r.get_mut(a)?, l.get_mut(b)?)),
+                    (Greater, Equal  ) => Some((r.get_mut(a)?, v)),
+                    _ => None,
+                }
+            },
+        }
+    }
+
+    /// Builds a perfectly balanced `Link` from the next `len` items of the
+    /// iterator, which must be sorted in ascending order by key. This is an
+    /// `O(n)` operation since no rotations are needed.
+    ///
+    fn from_sorted_iter<I>(items: &mut I, len: usize) -> Link<K, V>
+    where
+        I: Iterator<Item = (K, V)>,
+    {
+        if len == 0 {
+            return Empty;
+        }
+        let len_l        = len / 2;
+        let left         = Self::from_sorted_iter(items, len_l);
+        let (key, value) = items.next().expect("Not enough sorted items.");
+        let right        = Self::from_sorted_iter(items, len - len_l - 1);
+
+        Filled(Box::new(Node::with_children(key, value, left, right)))
+    }
+
+    /// Builds a perfectly balanced `Link` from a `Vec` of items sorted in
+    /// ascending order by key.
+    ///
+    fn from_sorted_vec(items: Vec<(K, V)>) -> Self
+    {
+        let len = items.len();
+        Self::from_sorted_iter(&mut items.into_iter(), len)
+    }
+
+    /// Moves every key and value out of the tree, consuming it, and pushes them
+    /// onto `items` in ascending order by key.
+    ///
+    fn drain_into(self, items: &mut Vec<(K, V)>)
+    {
+        if let Filled(node) = self {
+            let Node { key, value, left, right, .. } = *node;
+            left.drain_into(items);
+            items.push((key, value));
+            right.drain_into(items);
+        }
+    }
+
+    /// Internal implementation for `.split_at_nth()`. Consumes the tree and
+    /// returns a tree of the smallest `index` items and a tree of the rest.
+    /// The node on the path to the split point is used as the pivot to join
+    /// the sub-tree it keeps to the part of the split that came up from below.
+    ///
+    fn split_nth_internal(self, index: usize, stats: &mut Stats)
+        -> (Self, Self)
+    {
+        match self {
+            Filled(node) => {
+                let Node { key, value, left, right, .. } = *node;
+                let wt_l = left.weight_or_zero() as usize;
+                if index <= wt_l {
+                    let (ll, lr) = left.split_nth_internal(index, stats);
+                    (ll, Self::join_pivot(lr, key, value, right, stats))
+                } else {
+                    let index    = index - wt_l - 1;
+                    let (rl, rr) = right.split_nth_internal(index, stats);
+                    (Self::join_pivot(left, key, value, rl, stats), rr)
+                }
+            },
+            Empty => (Empty, Empty),
+        }
+    }
+
+    /// Joins two trees into one using the given key and value as the pivot.
+    /// All the keys in `left` must be less than `key`, and all the keys in
+    /// `right` must be greater. The shorter tree is attached at the point
+    /// along the taller tree's inner spine where the heights match, then the
+    /// nodes along the path are rebalanced. This is an `O(log n)` operation.
+    ///
+    fn join_pivot(left: Self, key: K, value: V, right: Self,
+                  stats: &mut Stats) -> Self
+    {
+        let h_l = left.height();
+        let h_r = right.height();
+
+        if h_l > h_r + 1 {
+            let mut t = left;
+            let     r = t.right.take_tree();
+            t.right   = Self::join_pivot(r, key, value, right, stats);
+            t.restore_balance(stats);
+            t
+        }
+        else if h_r > h_l + 1 {
+            let mut t = right;
+            let     l = t.left.take_tree();
+            t.left    = Self::join_pivot(left, key, value, l, stats);
+            t.restore_balance(stats);
+            t
+        }
+        else {
+            Filled(Box::new(Node::with_children(key, value, left, right)))
+        }
+    }
+
+}
+
+impl<K, V> Tree<K, V>
+where
+    K: Ord,
+{
+    /// Creates a new `Tree` populated with a `Node` holding the given key and
+    /// value.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::new_with_insert('a', 1);
+    /// assert_eq!(tree[&'a'], 1);
+    /// ```
+    /// 
+    pub fn new_with_insert(key: K, value: V) -> Self
+    {
+        Tree::from_root(Filled(Box::new(Node::new(key, value))))
+    }
+
+    /// Creates a new empty `Tree`.
+    /// ```
+    /// use avl_tree::*;
+    /// assert_eq!(Tree::<String, ()>::new().is_empty(), true);
+    /// ```
+    ///
+    pub fn new() -> Self
+    {
+        Tree::from_root(Empty)
+    }
+
+    /// Private constructor that wraps the given root in a `Tree`, starting its
+    /// rotation counts at zero.
+    ///
+    fn from_root(root: Link<K, V>) -> Self
+    {
+        Tree { root, stats: Stats::default() }
+    }
+
+    /// Inserts the given key and value into the binary tree. If the key was
+    /// already present, then `Some(V)` is returned holding the former value
+    /// of the key. If the key wasn't already present, `None` is returned.
+    /// ```
+    /// use avl_tree::*;
+    /// 
+    /// let mut tree = Tree::new();
+    /// assert_eq!(tree.insert("foo", 42), None);
+    /// assert_eq!(tree.insert("foo", 72), Some(42));
+    /// assert_eq!(tree[&"foo"], 72);
+    /// ```
+    ///
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    {
+        self.root.insert_within(key, value, 1, &mut self.stats)
+    }
+
+    /// Removes the given key from the tree and returns its value by moving it
+    /// out of the tree, or returns `None` if the key isn't present. Unlike
+    /// `.remove()`, neither the keys nor the values need to implement `Clone`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.insert("foo", vec![1, 2, 3]);
+    /// assert_eq!(tree.take(&"foo"), Some(vec![1, 2, 3]));
+    /// assert_eq!(tree.take(&"foo"), None);
+    /// ```
+    ///
+    pub fn take(&mut self, key: &K) -> Option<V>
+    {
+        self.root.take_within(key, 1, &mut self.stats)
+    }
+
+    /// Creates a new empty `RelaxedTree`, which only rotates its nodes when a
+    /// balance factor's magnitude exceeds `tol`. A tolerance of 1 behaves
+    /// exactly like a `Tree`. Larger tolerances perform fewer rotations on
+    /// insertion and removal in exchange for taller trees and slower lookups.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tol` is 0, since no tree of an even length can satisfy it.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::with_imbalance_tolerance(2);
+    /// for i in 0..100 {
+    ///     tree.insert(i, i * 2);
+    /// }
+    /// assert_eq!(tree.get(&40), Some(&80));
+    /// ```
+    ///
+    pub fn with_imbalance_tolerance(tol: usize) -> RelaxedTree<K, V>
+    {
+        assert!(tol >= 1, "The imbalance tolerance must be at least 1.");
+        RelaxedTree { tree: Tree::new(), tolerance: tol as isize }
+    }
+
+    /// Consumes the tree and returns its entry with the smallest key, along
+    /// with the tree of the remaining entries, or `None` if the tree is empty.
+    /// The remainder is rebalanced along the path to the removed node.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// let ((k, v), rest) = tree.split_first().unwrap();
+    /// assert_eq!((k, v), (1, 'a'));
+    /// assert_eq!(rest.len(), 1);
+    /// ```
+    ///
+    pub fn split_first(mut self) -> Option<((K, V), Tree<K, V>)>
+    {
+        if self.root.is_filled() {
+            let first = self.root.pop_min(&mut self.stats);
+            Some((first, self))
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the tree and returns its entry with the largest key, along
+    /// with the tree of the remaining entries, or `None` if the tree is empty.
+    /// The remainder is rebalanced along the path to the removed node.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// let ((k, v), rest) = tree.split_last().unwrap();
+    /// assert_eq!((k, v), (2, 'b'));
+    /// assert_eq!(rest.len(), 1);
+    /// ```
+    ///
+    pub fn split_last(mut self) -> Option<((K, V), Tree<K, V>)>
+    {
+        if self.root.is_filled() {
+            let last = self.root.pop_max(&mut self.stats);
+            Some((last, self))
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the tree and returns an iterator over its values in ascending
+    /// order by key. The values are moved out of the tree, so they don't need
+    /// to implement `Clone`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.insert(2, "two".to_string());
+    /// tree.insert(1, "one".to_string());
+    /// let values = tree.into_values().collect::<Vec<_>>();
+    /// assert_eq!(values, ["one", "two"]);
+    /// ```
+    ///
+    pub fn into_values(self) -> IntoValues<K, V>
+    {
+        IntoValues { iter: self.into_iter() }
+    }
+
+    /// Consumes the tree and returns an iterator over its keys in ascending
+    /// order. The keys are moved out of the tree, and the values are dropped
+    /// as the iteration goes, so neither needs to implement `Clone`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.insert("b", 2);
+    /// tree.insert("a", 1);
+    /// let keys = tree.into_keys().collect::<Vec<_>>();
+    /// assert_eq!(keys, ["a", "b"]);
+    /// ```
+    ///
+    pub fn into_keys(self) -> IntoKeys<K, V>
+    {
+        IntoKeys { iter: self.into_iter() }
+    }
+}
+
+impl<K, V> Link<K, V>
+where
+    K: Ord,
+{
+    /// Internal implementation for `.insert()`. Nodes along the path are only
+    /// rotated when the magnitude of their balance factor exceeds `tol`.
+    ///
+    fn insert_within(&mut self, key: K, value: V, tol: isize,
+                     stats: &mut Stats) -> Option<V>
+    {
+        use Ordering::*;
+        let mut ret = None;
+        match self {
+            Empty => {
+                *self = Filled(Box::new(Node::new(key, value)));
+            },
+            Filled(node) => {
+                match key.cmp(&node.key) {
+                    Less => {
+                        ret = node.left.insert_within(key, value, tol, stats);
+                    },
+                    Greater => {
+                        ret = node.right.insert_within(key, value, tol, stats);
+                    },
+                    Equal => {
+                        ret = Some(std::mem::replace(&mut node.value, value));
+                    },
+                }
+                // If ret.is_none() == true, tree changed size.
+                if ret.is_none() {
+                    self.grow_within(tol, stats);
+                }
+            },
+        }
+        ret
+    }
+
+    /// Inserts the given key and value only if the key isn't already present,
+    /// and returns the ordinal position of the new entry. The position is
+    /// counted on the way down, which lets callers find the new value again
+    /// without keeping a copy of the key. If the key is present, the tree is
+    /// left unchanged and the key and value are handed back. Nodes along the
+    /// path are only rotated when the magnitude of their balance factor
+    /// exceeds `tol`.
+    ///
+    fn insert_new_within(&mut self, key: K, value: V, tol: isize,
+                         stats: &mut Stats) -> Result<usize, (K, V)>
+    {
+        use Ordering::*;
+        let node = match self {
+            Filled(node) => node,
+            Empty => {
+                *self = Filled(Box::new(Node::new(key, value)));
+                return Ok(0);
+            },
+        };
+        let index = match key.cmp(&node.key) {
+            Less => {
+                node.left.insert_new_within(key, value, tol, stats)?
+            },
+            Greater => {
+                let wt_l = node.left.weight_or_zero() as usize;
+                wt_l + 1 + node.right.insert_new_within(key, value, tol, stats)?
+            },
+            Equal => {
+                return Err((key, value));
+            },
+        };
+        self.grow_within(tol, stats);
+        Ok(index)
+    }
+
+    /// Counts an entry that was added below the current node, then performs
+    /// the rotation its balance calls for, if any. Nodes are only rotated when
+    /// the magnitude of their balance factor exceeds `tol`.
+    ///
+    fn grow_within(&mut self, tol: isize, stats: &mut Stats)
+    {
+        if let Filled(node) = self {
+            node.update_weight();
+
+            let bf   = node.balance();
+            let bf_r = node.right.balance();
+            let bf_l = node.left.balance();
+
+            if bf > tol {
+                if bf_l > 0 {
+                    self.rotate_left_left(stats);
+                } 
+                else if bf_l < 0 {
+                    self.rotate_left_right(stats);
+                }
+            }
+            else if bf < -tol {
+                if bf_r < 0 {
+                    self.rotate_right_right(stats);
+                } 
+                else if bf_r > 0 {
+                    self.rotate_right_left(stats);
+                }
+            }
+            debug_assert!(self.balance().abs() <= bf.abs(),
+                          "Rotation left the node less balanced.");
+        }
+    }
+
+    /// Internal implementation for `.take()`. Nodes along the path are only
+    /// rotated when the magnitude of their balance factor exceeds `tol`.
+    ///
+    fn take_within(&mut self, key: &K, tol: isize, stats: &mut Stats)
+        -> Option<V>
+    {
+        use Ordering::*;
+        let node = match self {
+            Filled(node) => node,
+            Empty => return None,
+        };
+        let ret = match key.cmp(&node.key) {
+            Less => {
+                node.left.take_within(key, tol, stats)
+            },
+            Greater => {
+                node.right.take_within(key, tol, stats)
+            },
+            Equal => {
+                return Some(self.take_root_within(tol, stats).1);
+            },
+        };
+        if ret.is_some() {
+            self.restore_balance_within(tol, stats);
+        }
+        ret
+    }
+
+    /// Internal implementation for `.pop_nth()`. Descends to the node at the
+    /// ordinal position `index`, which must be within range, and unlinks it.
+    /// The nodes along the path are rebalanced.
+    ///
+    fn pop_nth_internal(&mut self, index: usize, stats: &mut Stats) -> (K, V)
+    {
+        use Ordering::*;
+        let wt_l = self.left.weight_or_zero() as usize;
+        let ret  = match index.cmp(&wt_l) {
+            Less => {
+                self.left.pop_nth_internal(index, stats)
+            },
+            Greater => {
+                self.right.pop_nth_internal(index - wt_l - 1, stats)
+            },
+            Equal => {
+                return self.take_root_within(1, stats);
+            },
+        };
+        self.restore_balance(stats);
+        ret
+    }
+
+    /// Unlinks the root node of a non-empty tree and returns its key and
+    /// value. A root with two children is replaced by the smallest node of
+    /// its right sub-tree; otherwise its only child, if any, takes its place.
+    /// Nodes are only rotated when the magnitude of their balance factor
+    /// exceeds `tol`.
+    ///
+    fn take_root_within(&mut self, tol: isize, stats: &mut Stats) -> (K, V)
+    {
+        if self.left.is_filled() && self.right.is_filled() {
+            let (k, v) = self.right.pop_min_within(tol, stats);
+            let key    = std::mem::replace(&mut self.key, k);
+            let value  = std::mem::replace(&mut self.value, v);
+            self.restore_balance_within(tol, stats);
+            (key, value)
+        } else {
+            let child = if self.left.is_filled() { self.left.take_tree()  }
+                        else                     { self.right.take_tree() };
+            match std::mem::replace(self, child) {
+                Filled(node) => (node.key, node.value),
+                Empty => panic!("Attempt to pop from an Empty Tree."),
+            }
+        }
+    }
+
+    /// Unlinks the node holding the smallest key from a non-empty tree, and
+    /// returns its key and value. The nodes along the path are rebalanced.
+    ///
+    fn pop_min(&mut self, stats: &mut Stats) -> (K, V)
+    {
+        self.pop_min_within(1, stats)
+    }
+
+    /// Internal implementation for `.pop_min()`. Nodes along the path are only
+    /// rotated when the magnitude of their balance factor exceeds `tol`.
+    ///
+    fn pop_min_within(&mut self, tol: isize, stats: &mut Stats) -> (K, V)
+    {
+        if self.left.is_filled() {
+            let ret = self.left.pop_min_within(tol, stats);
+            self.restore_balance_within(tol, stats);
+            ret
+        } else {
+            let child = self.right.take_tree();
+            match std::mem::replace(self, child) {
+                Filled(node) => (node.key, node.value),
+                Empty => panic!("Attempt to pop from an Empty Tree."),
+            }
+        }
+    }
+
+    /// Unlinks the node holding the largest key from a non-empty tree, and
+    /// returns its key and value. The nodes along the path are rebalanced.
+    ///
+    fn pop_max(&mut self, stats: &mut Stats) -> (K, V)
+    {
+        if self.right.is_filled() {
+            let ret = self.right.pop_max(stats);
+            self.restore_balance(stats);
+            ret
+        } else {
+            let child = self.left.take_tree();
+            match std::mem::replace(self, child) {
+                Filled(node) => (node.key, node.value),
+                Empty => panic!("Attempt to pop from an Empty Tree."),
+            }
+        }
+    }
+}
+
+impl<K, V> Link<K, V>
+{
+    /// Returns the height of the tree, which is the number of nodes on the
+    /// longest path from its root to a leaf, or 0 if it's `Empty`. Each node
+    /// stores the height of its sub-tree, so this is an `O(1)` operation.
+    /// 
+    fn height(&self) -> isize
+    {
+        match self {
+            Filled(node) => node.height,
+            Empty => 0,
+        }
+    }
+
+    /// Returns a value indicating whether the tree is balanced or not, with
+    /// negative values indicating the tree is heavy on the right, and
+    /// positive values indicating the tree is heavy on the left. The value 0
+    /// indicates a perfectly balanced tree/sub-tree.
+    /// 
+    fn balance(&self) -> isize 
+    {
+        match self {
+            Filled(node) => node.left.height() - node.right.height(),
+            Empty => 0,
+        }
+    }
+
+    /// Moves the tree from it's former location, replacing it with `Empty` and
+    /// returns the moved value to the caller giving it ownership.
+    ///
+    fn take_tree(&mut self) -> Link<K, V>
+    {
+        std::mem::take(self)
+    }
+
+    /// Indicates whether a `Link` has nodes (`true`), or is `Empty` (`false`). 
+    /// 
+    fn is_filled(&self) -> bool
+    {
+        matches!(self, Filled(_))
+    }
+
+    /// Performs a left-left rotation on the current `Link`. These methods are
+    /// used to keep the tree in balance, so both left and right sub-trees 
+    /// grow or shrink at nearly the same rate. The name of the method can
+    /// be read as, "a left rotation is performed on the left branch." The
+    /// node within the current tree will be updated to hold the former left
+    /// node. If the nodes a rotation moves aren't all there, debug builds
+    /// panic at the rotation, while release builds leave the tree unchanged.
+    /// 
+    fn rotate_left_left(&mut self, stats: &mut Stats)
+    {
+        let can_rotate = matches!(self, Filled(n) if n.left.is_filled());
+        debug_assert!(can_rotate, "Left-left rotation without left child.");
+        if !can_rotate {
+            return;
+        }
+        let corrects = self.balance() == 2
+                    && self.left.balance().abs() <= 1;
+
+        let mut n = self.take_tree();
+        let mut t = n.left.take_tree();
+        n.left    = t.right.take_tree();
+        n.update_weight();
+        t.right   = n;
+        *self     = t;
+        self.update_weight();
+        stats.tally();
+        debug_assert!(self.rotated_sound(corrects),
+                      "Left-left rotation failed.");
+    }
+
+    /// Performs a right-right rotation on the current `Link`. The `Link`'s
+    /// node will be updated to hold the former right node.
+    /// 
+    fn rotate_right_right(&mut self, stats: &mut Stats)
+    {
+        let can_rotate = matches!(self, Filled(n) if n.right.is_filled());
+        debug_assert!(can_rotate, "Right-right rotation without right child.");
+        if !can_rotate {
+            return;
+        }
+        let corrects = self.balance() == -2
+                    && self.right.balance().abs() <= 1;
+
+        let mut n = self.take_tree();
+        let mut t = n.right.take_tree();
+        n.right   = t.left.take_tree();
+        n.update_weight();
+        t.left    = n;
+        *self     = t;
+        self.update_weight();
+        stats.tally();
+        debug_assert!(self.rotated_sound(corrects),
+                      "Right-right rotation failed.");
+    }
+
+    /// Performs a right-left rotation on the current `Link`.
+    /// 
+    fn rotate_right_left(&mut self, stats: &mut Stats)
+    {
+        let can_rotate = matches!(self, Filled(n)
+                                  if matches!(&n.right, Filled(r)
+                                              if r.left.is_filled()));
+        debug_assert!(can_rotate, "Right-left rotation without a grandchild.");
+        if !can_rotate {
+            return;
+        }
+        let corrects = self.balance() == -2
+                    && self.right.balance().abs()      <= 1
+                    && self.right.left.balance().abs() <= 1;
+
+        let mut n  = self.take_tree();
+        let mut t2 = n.right.left.take_tree();
+        let mut t1 = n.right.take_tree();
+        n.right    = t2.left.take_tree();
+        t1.left    = t2.right.take_tree();
+        n.update_weight();
+        t1.update_weight();
+        t2.left    = n;
+        t2.right   = t1;
+        *self      = t2;
+        self.update_weight();
+        stats.tally();
+        debug_assert!(self.rotated_sound(corrects),
+                      "Right-left rotation failed.");
+    }
+
+    /// Performs a left-right rotation on the current `Link`.
+    /// 
+    fn rotate_left_right(&mut self, stats: &mut Stats)
+    {
+        let can_rotate = matches!(self, Filled(n)
+                                  if matches!(&n.left, Filled(l)
+                                              if l.right.is_filled()));
+        debug_assert!(can_rotate, "Left-right rotation without a grandchild.");
+        if !can_rotate {
+            return;
+        }
+        let corrects = self.balance() == 2
+                    && self.left.balance().abs()       <= 1
+                    && self.left.right.balance().abs() <= 1;
+
+        let mut n  = self.take_tree();
+        let mut t2 = n.left.right.take_tree();
+        let mut t1 = n.left.take_tree();
+        n.left     = t2.right.take_tree();
+        t1.right   = t2.left.take_tree();
+        n.update_weight();
+        t1.update_weight();
+        t2.right   = n;
+        t2.left    = t1;
+        *self      = t2;
+        self.update_weight();
+        stats.tally();
+        debug_assert!(self.rotated_sound(corrects),
+                      "Left-right rotation failed.");
+    }
+
+    /// Checks the invariants of a tree that was just rotated. The weights of
+    /// the new root and its children must agree with the weights of their
+    /// sub-trees. If the rotation `corrects` a node that was 2 levels heavier
+    /// on the side it rotates from, and the nodes it lifts were balanced, as
+    /// in every rotation of a strictly balanced tree, the new root and the
+    /// children it was rotated above must all be balanced as well. Rotations
+    /// verify this in debug builds so that a bug in them is caught where it
+    /// happens rather than at a later lookup.
+    ///
+    fn rotated_sound(&self, corrects: bool) -> bool
+    {
+        let sound = |t: &Link<K, V>| match t {
+            Filled(node) => {
+                node.weight == 1 + node.left.weight_or_zero()
+                                 + node.right.weight_or_zero()
+                && (!corrects || node.balance().abs() <= 1)
+            },
+            Empty => true,
+        };
+        match self {
+            Filled(node) => {
+                sound(self) && sound(&node.left) && sound(&node.right)
+            },
+            Empty => false,
+        }
+    }
+
+    /// Recalculates the weight and height of the tree's node from those stored
+    /// by its children. The rotations invoke this on each node they move, from
+    /// the bottom up, since those are the only nodes whose sub-trees change.
+    /// 
+    fn update_weight(&mut self)
+    {
+        if let Filled(node) = self {
+            node.update_weight();
+        }
+    }
+
+    /// Recalculates the weight of the current node from its children and
+    /// performs any rotation needed to bring it back into balance. This is
+    /// used after one of the node's sub-trees has been replaced outright, on
+    /// the way back up from a removal or a join. The replacement's height
+    /// differs from the former sub-tree's by at most one level, so a single
+    /// or double rotation of the node is enough, which keeps those operations
+    /// `O(log n)`.
+    ///
+    fn restore_balance(&mut self, stats: &mut Stats)
+    {
+        self.restore_balance_within(1, stats);
+    }
+
+    /// Internal implementation for `.restore_balance()`. A rotation is only
+    /// performed when the magnitude of the balance factor exceeds `tol`.
+    ///
+    fn restore_balance_within(&mut self, tol: isize, stats: &mut Stats)
+    {
+        if let Filled(node) = self {
+            node.update_weight();
+
+            let bf   = node.balance();
+            let bf_r = node.right.balance();
+            let bf_l = node.left.balance();
+
+            if bf > tol {
+                if bf_l >= 0 {
+                    self.rotate_left_left(stats);
+                }
+                else {
+                    self.rotate_left_right(stats);
+                }
+            }
+            else if bf < -tol {
+                if bf_r <= 0 {
+                    self.rotate_right_right(stats);
+                }
+                else {
+                    self.rotate_right_left(stats);
+                }
+            }
+            else {
+                return;
+            }
+            debug_assert!(self.balance().abs() <= bf.abs(),
+                          "Rotation left the node less balanced.");
+        }
+    }
+
+    /// Counts the nodes of the tree bottom-up, checking each node's stored
+    /// weight against the count of its sub-tree along the way. Returns the
+    /// count, or `None` as soon as a mismatched weight is found.
+    ///
+    fn verified_weight(&self) -> Option<isize>
+    {
+        match self {
+            Filled(node) => {
+                let wt_l   = node.left.verified_weight()?;
+                let wt_r   = node.right.verified_weight()?;
+                let weight = 1 + wt_l + wt_r;
+                if node.weight == weight { Some(weight) } else { None }
+            },
+            Empty => Some(0),
+        }
+    }
+
+    /// Returns the number of edges on the longest path from the root of the
+    /// tree to a leaf, or 0 if the tree is `Empty`. Unlike `.height()`, this
+    /// visits every node to measure the actual shape of the tree.
+    ///
+    fn edge_height(&self) -> usize
+    {
+        match self {
+            Filled(node) => {
+                let below = |t: &Link<K, V>| match t {
+                    Filled(_) => 1 + t.edge_height(),
+                    Empty     => 0,
+                };
+                below(&node.left).max(below(&node.right))
+            },
+            Empty => 0,
+        }
+    }
+
+    /// Returns the weight (number of nodes) of the tree, or 0 if it's `Empty`.
+    ///
+    fn weight_or_zero(&self) -> isize
+    {
+        match self {
+            Filled(node) => node.weight,
+            Empty => 0,
+        }
+    }
+}
+
+/// Returns `true` if `key` is within the lower bound `start` of a range.
+///
+fn after_start<K: Ord>(start: Bound<&K>, key: &K) -> bool
+{
+    match start {
+        Bound::Included(start) => key >= start,
+        Bound::Excluded(start) => key >  start,
+        Bound::Unbounded       => true,
+    }
+}
+
+/// Returns `true` if `key` is within the upper bound `end` of a range.
+///
+fn before_end<K: Ord>(end: Bound<&K>, key: &K) -> bool
+{
+    match end {
+        Bound::Included(end) => key <= end,
+        Bound::Excluded(end) => key <  end,
+        Bound::Unbounded     => true,
+    }
+}
+
+/// Indicates which of the two trees walked by `Tree::join()` a key was found
+/// in, and holds the values associated with it.
+///
+/// # Variants
+///
+/// * `Left`    - The key is only in the tree `join` was called on.
+/// * `Right`   - The key is only in the other tree.
+/// * `Both`    - The key is in both trees.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinSide<L, R>
+{
+    Left(L),
+    Right(R),
+    Both(L, R),
+}
+
+/// A difference between two trees, as found by `Tree::diff()`.
+///
+/// # Variants
+///
+/// * `Added`     - The key and value are only in the other tree.
+/// * `Removed`   - The key and value are only in the tree `diff` was called on.
+/// * `Modified`  - The key is in both trees, with different values.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change<'a, K, V>
+{
+    Added(&'a K, &'a V),
+    Removed(&'a K, &'a V),
+    Modified { key: &'a K, old: &'a V, new: &'a V },
+}
+
+/// A single change to apply to a `Tree` with `Tree::apply()`.
+///
+/// # Variants
+///
+/// * `Insert`  - Inserts the key and value, replacing any existing value.
+/// * `Remove`  - Removes the key, if it's present.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<K, V>
+{
+    Insert(K, V),
+    Remove(K),
+}
+
+/// Counts of the changes made by `Tree::apply()`. An `Insert` of a new key
+/// counts as `inserted`, and one of an existing key as `updated`. Only a
+/// `Remove` of a key that was present counts as `removed`.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ApplyStats
+{
+    pub inserted : usize,
+    pub updated  : usize,
+    pub removed  : usize,
+}
+
+/// An iterator over the key/value pairs of a `Tree`, yielded in ascending
+/// order by key. It holds a stack of the nodes whose left sub-trees are being
+/// visited, so it only requires `O(log n)` space. It also keeps a count of the
+/// items left to yield, which it reports as an `ExactSizeIterator`. Created by
+/// `Tree::iter()`.
+///
+pub struct Iter<'a, K, V>
+{
+    stack     : Vec<&'a Node<K, V>>,
+    remaining : usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V>
+{
+    /// Creates a new iterator positioned at the smallest key in `tree`.
+    ///
+    fn new(tree: &'a Link<K, V>) -> Self
+    {
+        let     remaining = Self::weight_of(tree);
+        let mut iter      = Iter { stack: Vec::new(), remaining };
+        iter.push_left(tree);
+        iter
+    }
+
+    /// Creates a new iterator positioned at the item with the ordinal position
+    /// `index`. The stack is seeded by the same descent as `.get_nth()`. If
+    /// `index` is out of range, the iterator is already exhausted.
+    ///
+    fn new_at_nth(mut tree: &'a Link<K, V>, mut index: usize) -> Self
+    {
+        let     remaining = Self::weight_of(tree).saturating_sub(index);
+        let mut iter      = Iter { stack: Vec::new(), remaining };
+        while let Filled(node) = tree {
+            let wt_l = match &node.left { Filled(n) => n.weight as usize,
+                                          Empty     => 0,                 };
+            if index < wt_l {
+                iter.stack.push(node);
+                tree = &node.left;
+            } 
+            else if index == wt_l {
+                iter.stack.push(node);
+                break;
+            } 
+            else {
+                index -= wt_l + 1;
+                tree   = &node.right;
+            }
+        }
+        iter
+    }
+
+    /// Creates a new iterator positioned at the smallest key that's greater
+    /// than or equal to `key`. The stack is seeded with the nodes on the
+    /// search path where the descent went left, along with the matching node.
+    ///
+    fn new_at_key(mut tree: &'a Link<K, V>, key: &K) -> Self
+    where
+        K: Ord,
+    {
+        use Ordering::*;
+        let mut iter = Iter { stack: Vec::new(), remaining: 0 };
+        while let Filled(node) = tree {
+            match key.cmp(&node.key) {
+                Less => {
+                    iter.stack.push(node);
+                    iter.remaining += 1 + Self::weight_of(&node.right);
+                    tree = &node.left;
+                },
+                Greater => {
+                    tree = &node.right;
+                },
+                Equal => {
+                    iter.stack.push(node);
+                    iter.remaining += 1 + Self::weight_of(&node.right);
+                    break;
+                },
+            }
+        }
+        iter
+    }
+
+    /// Creates a new iterator positioned at the smallest key that's within the
+    /// lower bound `start`. The stack is seeded with the nodes on the search
+    /// path whose keys are within the bound, where the descent went left.
+    ///
+    fn new_at_bound(mut tree: &'a Link<K, V>, start: Bound<&K>) -> Self
+    where
+        K: Ord,
+    {
+        let mut iter = Iter { stack: Vec::new(), remaining: 0 };
+        while let Filled(node) = tree {
+            if after_start(start, &node.key) {
+                iter.stack.push(node);
+                iter.remaining += 1 + Self::weight_of(&node.right);
+                tree = &node.left;
+            } else {
+                tree = &node.right;
+            }
+        }
+        iter
+    }
+
+    /// Pushes the given tree's node and all the nodes along its left spine
+    /// onto the stack.
+    ///
+    fn push_left(&mut self, mut tree: &'a Link<K, V>)
+    {
+        while let Filled(node) = tree {
+            self.stack.push(node);
+            tree = &node.left;
+        }
+    }
+
+    /// Returns the number of nodes in the given tree.
+    ///
+    fn weight_of(tree: &Link<K, V>) -> usize
+    {
+        match tree {
+            Filled(node) => node.weight as usize,
+            Empty => 0,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+{
+    type Item = (&'a K, &'a V);
+
+    /// Returns the next key/value pair in order, or `None` when the iteration
+    /// is finished.
+    ///
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        self.remaining -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    /// Returns the exact number of items left to yield as both bounds.
+    ///
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+/// An iterator that moves the key/value pairs out of a `Tree`, yielding them
+/// in ascending order by key. Like `Iter`, it holds a stack of the entries
+/// whose left sub-trees are being visited, along with their right sub-trees,
+/// which are still to be visited. Created by `Tree::into_iter()`.
+///
+pub struct IntoIter<K, V>
+{
+    stack: Vec<(K, V, Link<K, V>)>,
+}
+
+impl<K, V> IntoIter<K, V>
+{
+    /// Creates a new iterator positioned at the smallest key in `tree`.
+    ///
+    fn new(tree: Link<K, V>) -> Self
+    {
+        let mut iter = IntoIter { stack: Vec::new() };
+        iter.push_left(tree);
+        iter
+    }
+
+    /// Unpacks the given tree's node, and all the nodes along its left spine,
+    /// onto the stack.
+    ///
+    fn push_left(&mut self, mut tree: Link<K, V>)
+    {
+        while let Filled(node) = tree {
+            let Node { key, value, left, right, .. } = *node;
+            self.stack.push((key, value, right));
+            tree = left;
+        }
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V>
+{
+    type Item = (K, V);
+
+    /// Returns the next key/value pair in order, or `None` when the iteration
+    /// is finished.
+    ///
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let (key, value, right) = self.stack.pop()?;
+        self.push_left(right);
+        Some((key, value))
+    }
+}
+
+/// An iterator that moves the values out of a `Tree`, yielding them in
+/// ascending order by key. Created by `Tree::into_values()`.
+///
+pub struct IntoValues<K, V>
+{
+    iter: IntoIter<K, V>,
+}
+
+impl<K, V> Iterator for IntoValues<K, V>
+{
+    type Item = V;
+
+    /// Returns the next value in order, or `None` when the iteration is
+    /// finished.
+    ///
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        self.iter.next().map(|(_, value)| value)
+    }
+}
+
+/// An iterator that moves the keys out of a `Tree`, yielding them in ascending
+/// order. Created by `Tree::into_keys()`.
+///
+pub struct IntoKeys<K, V>
+{
+    iter: IntoIter<K, V>,
+}
+
+impl<K, V> Iterator for IntoKeys<K, V>
+{
+    type Item = K;
+
+    /// Returns the next key in order, or `None` when the iteration is
+    /// finished.
+    ///
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        self.iter.next().map(|(key, _)| key)
+    }
+}
+
+/// An iterator over the keys of a `Tree` and mutable references to their
+/// values, yielded in ascending order by key. Since a node's key and value are
+/// handed out while it's still on the stack, the stack holds the borrowed
+/// parts of each node rather than the nodes themselves. Created by
+/// `Tree::iter_mut()`.
+///
+pub struct IterMut<'a, K, V>
+{
+    stack: Vec<(&'a K, &'a mut V, &'a mut Link<K, V>)>,
+}
+
+impl<'a, K, V> IterMut<'a, K, V>
+{
+    /// Creates a new iterator positioned at the smallest key in `tree`.
+    ///
+    fn new(tree: &'a mut Link<K, V>) -> Self
+    {
+        let mut iter = IterMut { stack: Vec::new() };
+        iter.push_left(tree);
+        iter
+    }
+
+    /// Creates a new iterator positioned at the smallest key that's within the
+    /// lower bound `start`. The stack is seeded with the nodes on the search
+    /// path whose keys are within the bound, where the descent went left.
+    ///
+    fn new_at_bound(mut tree: &'a mut Link<K, V>, start: Bound<&K>) -> Self
+    where
+        K: Ord,
+    {
+        let mut iter = IterMut { stack: Vec::new() };
+        while let Filled(node) = tree {
+            let Node { key, value, left, right, .. } = &mut **node;
+            if after_start(start, key) {
+                iter.stack.push((key, value, right));
+                tree = left;
+            } else {
+                tree = right;
+            }
+        }
+        iter
+    }
+
+    /// Pushes the parts of the given tree's node, and of all the nodes along
+    /// its left spine, onto the stack.
+    ///
+    fn push_left(&mut self, mut tree: &'a mut Link<K, V>)
+    {
+        while let Filled(node) = tree {
+            let Node { key, value, left, right, .. } = &mut **node;
+            self.stack.push((key, value, right));
+            tree = left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V>
+{
+    type Item = (&'a K, &'a mut V);
+
+    /// Returns the next key and mutable value in order, or `None` when the
+    /// iteration is finished.
+    ///
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let (key, value, right) = self.stack.pop()?;
+        self.push_left(right);
+        Some((key, value))
+    }
+}
+
+/// A cursor that can be moved forward and backward over the entries of a
+/// `Tree` in sorted order. It holds the path of nodes from the root to the
+/// entry it's positioned at, so each step is `O(log n)` at worst, and `O(1)`
+/// on average. When the path is empty, the cursor is positioned off the ends
+/// of the tree; stepping forward from there moves it to the first entry, and
+/// stepping backward moves it to the last. Created by `Tree::cursor_at()`.
+///
+pub struct Cursor<'a, K, V>
+{
+    root : &'a Link<K, V>,
+    path : Vec<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Cursor<'a, K, V>
+{
+    /// Returns the key and value the cursor is positioned at, or `None` if it's
+    /// positioned off the ends of the tree.
+    ///
+    pub fn key_value(&self) -> Option<(&'a K, &'a V)>
+    {
+        self.path.last().map(|node| (&node.key, &node.value))
+    }
+
+    /// Moves the cursor to the next entry in sorted order. If the cursor is at
+    /// the last entry, it moves off the end of the tree. If it's off the ends,
+    /// it moves to the first entry.
+    ///
+    pub fn move_next(&mut self)
+    {
+        match self.path.last() {
+            Some(node) if matches!(node.right, Filled(_)) => {
+                let right = &node.right;
+                self.push_spine(right, true);
+            },
+            Some(_) => {
+                self.pop_until(true);
+            },
+            None => {
+                let root = self.root;
+                self.push_spine(root, true);
+            },
+        }
+    }
+
+    /// Moves the cursor to the previous entry in sorted order. If the cursor
+    /// is at the first entry, it moves off the start of the tree. If it's off
+    /// the ends, it moves to the last entry.
+    ///
+    pub fn move_prev(&mut self)
+    {
+        match self.path.last() {
+            Some(node) if matches!(node.left, Filled(_)) => {
+                let left = &node.left;
+                self.push_spine(left, false);
+            },
+            Some(_) => {
+                self.pop_until(false);
+            },
+            None => {
+                let root = self.root;
+                self.push_spine(root, false);
+            },
+        }
+    }
+
+    /// Pushes the node of the given tree onto the path followed by all the
+    /// nodes along its left spine (`leftward == true`), or its right spine.
+    ///
+    fn push_spine(&mut self, mut tree: &'a Link<K, V>, leftward: bool)
+    {
+        while let Filled(node) = tree {
+            self.path.push(node);
+            tree = if leftward { &node.left } else { &node.right };
+        }
+    }
+
+    /// Pops nodes off the path until one is popped that was the left child of
+    /// its parent (`from_left == true`), or the right child. The parent is
+    /// then the next (or previous) entry. If no such node is found, the path
+    /// ends up empty.
+    ///
+    fn pop_until(&mut self, from_left: bool)
+    {
+        while let Some(child) = self.path.pop() {
+            if let Some(parent) = self.path.last() {
+                let side = if from_left { &parent.left } else { &parent.right };
+                if matches!(side, Filled(n) if std::ptr::eq(&**n, child)) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// A `Tree` that tolerates a larger imbalance between the heights of each
+/// node's sub-trees before rotating, trading lookup speed for fewer rotations
+/// on insertion and removal. It dereferences to the `Tree` it wraps for reads,
+/// while changes go through its own `.insert()` and `.remove()` so that the
+/// tolerance is applied. Created by `Tree::with_imbalance_tolerance()`.
+///
+#[derive(Clone, Debug)]
+pub struct RelaxedTree<K, V>
+{
+    tree      : Tree<K, V>,
+    tolerance : isize,
+}
+
+impl<K, V> RelaxedTree<K, V>
+where
+    K: Ord,
+{
+    /// Inserts the given key and value into the tree, rotating only the nodes
+    /// whose balance factors exceed the tolerance. Returns the former value of
+    /// the key if it was already present.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::with_imbalance_tolerance(3);
+    /// assert_eq!(tree.insert("foo", 42), None);
+    /// assert_eq!(tree.insert("foo", 72), Some(42));
+    /// ```
+    ///
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    {
+        let tree = &mut self.tree;
+        tree.root.insert_within(key, value, self.tolerance, &mut tree.stats)
+    }
+
+    /// Removes the given key from the tree and returns its value by moving it
+    /// out, or returns `None` if the key isn't present. Like `Tree::take()`,
+    /// neither the keys nor the values need to implement `Clone`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::with_imbalance_tolerance(2);
+    /// tree.insert("foo", 42);
+    /// assert_eq!(tree.remove(&"foo"), Some(42));
+    /// assert_eq!(tree.remove(&"foo"), None);
+    /// ```
+    ///
+    pub fn remove(&mut self, key: &K) -> Option<V>
+    {
+        let tree = &mut self.tree;
+        tree.root.take_within(key, self.tolerance, &mut tree.stats)
+    }
+
+    /// Returns the balance factor magnitude the tree tolerates before it
+    /// rotates.
+    ///
+    pub fn tolerance(&self) -> usize
+    {
+        self.tolerance as usize
+    }
+
+    /// Consumes the wrapper and returns the `Tree` it holds. Later changes to
+    /// the returned tree are balanced strictly again.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut relaxed = Tree::with_imbalance_tolerance(2);
+    /// relaxed.insert(1, 'a');
+    /// let tree = relaxed.into_inner();
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    ///
+    pub fn into_inner(self) -> Tree<K, V>
+    {
+        self.tree
+    }
+}
+
+impl<K, V> Deref for RelaxedTree<K, V>
+{
+    type Target = Tree<K, V>;
+
+    /// Gives read access to the wrapped `Tree`.
+    ///
+    fn deref(&self) -> &Self::Target
+    {
+        &self.tree
+    }
+}
+
+impl<K, V> PartialEq for Tree<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + PartialEq,
+{
+    /// Two trees are equal if they hold the same keys and values, regardless
+    /// of the shape of the trees.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut t1 = Tree::new();
+    /// let mut t2 = Tree::new();
+    /// for i in 0..10 { t1.insert(i, i); }
+    /// for i in (0..10).rev() { t2.insert(i, i); }
+    /// assert_eq!(t1, t2);
+    /// ```
+    ///
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K, V> Eq for Tree<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Eq,
+{
+}
+
+impl<K, V> PartialOrd for Tree<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + PartialOrd,
+{
+    /// Compares trees lexicographically by their in-order key/value pairs,
+    /// regardless of the shape of the trees.
+    /// 
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>
+    {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K, V> Ord for Tree<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Ord,
+{
+    /// Compares trees lexicographically by their in-order key/value pairs.
+    /// This is consistent with `PartialEq`, so trees can be sorted or used as
+    /// keys in ordered containers.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let t1 = Tree::from_sorted_slice(&[(1, 'z'), (5, 'z')]);
+    /// let t2 = Tree::from_sorted_slice(&[(2, 'a')]);
+    /// assert!(t1 < t2);
+    /// ```
+    ///
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K, V> Hash for Tree<K, V>
+where
+    K: Clone + Ord + Hash,
+    V: Clone + Hash,
+{
+    /// Hashes the length of the tree followed by each key and value in order.
+    /// This is consistent with `PartialEq`, so equal trees of different shapes
+    /// produce the same hash.
+    ///
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        state.write_usize(self.len());
+        for (k, v) in self.iter() {
+            k.hash(state);
+            v.hash(state);
+        }
+    }
+}
+
+impl<K, V> Clone for Tree<K, V>
+where
+    K: Clone,
+    V: Clone,
+{
+    /// Creates a deep copy of the tree, including every node.
+    /// 
+    fn clone(&self) -> Self
+    {
+        Tree { root: self.root.clone(), stats: self.stats }
+    }
+
+    /// Overwrites the tree with a deep copy of `source`. Wherever both trees
+    /// have a node in the same position, the existing node's allocation is
+    /// reused rather than being freed and allocated again. This makes it cheap
+    /// to repeatedly refresh a tree from another of a similar shape.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut t1 = Tree::new();
+    /// let mut t2 = Tree::new();
+    /// for i in 0..10 { t1.insert(i, i); }
+    /// for i in 5..15 { t2.insert(i, i); }
+    ///
+    /// t2.clone_from(&t1);
+    /// assert_eq!(t1, t2);
+    /// ```
+    ///
+    fn clone_from(&mut self, source: &Self)
+    {
+        self.root.clone_from(&source.root);
+        self.stats = source.stats;
+    }
+}
+
+impl<K, V> Default for Tree<K, V>
+{
+    /// Implements the default value for `Tree`, which is an empty tree.
+    /// 
+    fn default() -> Self { 
+        Tree { root: Empty, stats: Stats::default() }
+    }
+}
+
+impl<K, V> Deref for Tree<K, V>
+{
+    type Target = Node<K, V>;
+
+    /// Implements `Deref` for the `Tree`. This makes the fields of the `Node`
+    /// at the root of the tree accessible with minimal syntax.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if the tree is empty.
+    /// 
+    fn deref(&self) -> &Self::Target {
+        &self.root
+    }
+}
+
+impl<K, V> DerefMut for Tree<K, V>
+{
+    /// Complements the implementation of `Deref` by giving access to mutable
+    /// `Node` fields with minimal syntax.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if the tree is empty.
+    /// 
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.root
+    }
+}
+
+impl<K, V> Clone for Link<K, V>
+where
+    K: Clone,
+    V: Clone,
+{
+    /// Creates a deep copy of the sub-tree, including every node.
+    /// 
+    fn clone(&self) -> Self
+    {
+        match self {
+            Filled(node) => Filled(node.clone()),
+            Empty => Empty,
+        }
+    }
+
+    /// Overwrites the sub-tree with a deep copy of `source`, reusing the
+    /// allocation of each node that has a counterpart in `source`.
+    ///
+    fn clone_from(&mut self, source: &Self)
+    {
+        match (self, source) {
+            (Filled(dst), Filled(src)) => {
+                dst.key.clone_from(&src.key);
+                dst.value.clone_from(&src.value);
+                dst.weight = src.weight;
+                dst.height = src.height;
+                dst.left.clone_from(&src.left);
+                dst.right.clone_from(&src.right);
+            },
+            (dst, src) => {
+                *dst = src.clone();
+            },
+        }
+    }
+}
+
+impl<K, V> Default for Link<K, V>
+{
+    /// Implements the default value for `Link`. This is needed as part of the
+    /// `.take_tree()` feature.
+    /// 
+    fn default() -> Self { 
+        Empty
+    }
+}
+
+impl<K, V> Deref for Link<K, V>
+{
+    type Target = Node<K, V>;
+
+    /// Implements `Deref` for the `Link`. This makes the fields of the `Node`
+    /// contained in the `Filled` variant accessible with minimal syntax.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if the link is `Empty`.
+    /// 
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Filled(node) => node,
+            Empty => panic!("Attempt to dereference an Empty Tree."),
+        }
+    }
+}
+
+impl<K, V> DerefMut for Link<K, V>
+{
+    /// Complements the implementation of `Deref` by giving access to mutable
+    /// `Node` fields with minimal syntax.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if the link is `Empty`.
+    /// 
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Filled(node) => node,
+            Empty => panic!("Attempt to dereference an Empty Tree."),
+        }
+    }
+}
+
+impl<K, V> Index<&K> for Tree<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    type Output = V;
+
+    /// Gives the tree the square bracket indexing feature. The tree keys are
+    /// used to index their related values.
+    /// ```
+    /// use avl_tree::*;
+    /// let tree = Tree::new_with_insert("foo", 1);
+    /// assert_eq!(tree[&"foo"], 1);
+    /// ```
+    ///
+    fn index(&self, key: &K) -> &Self::Output
+    {
+        match self.get(key) {
+            Some(v) => v,
+            None => panic!("Attempt to read non-existent key."),
+        }
+    }
+}
+
+impl<K, V> IndexMut<&K> for Tree<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    /// Gives the tree the indexing feature so it behaves like a dictionary
+    /// which supports square bracket indexing.
+    /// ```
+    /// use avl_tree::*;
+    /// let mut tree = Tree::new_with_insert("foo", 1);
+    /// tree[&"foo"] += 1;
+    /// assert_eq!(tree[&"foo"], 2);
+    /// ```
+    /// 
+    fn index_mut(&mut self, key: &K) -> &mut Self::Output
+    {
+        match self.get_mut(key) {
+            Some(v) => v,
+            None => panic!("Key is not in the tree."),
+        }
+    }
+}
+
+impl<K, V> IntoIterator for Tree<K, V>
+{
+    type Item     = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Consumes the tree, moving its key/value pairs out in ascending order.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree  = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// let items = tree.into_iter().collect::<Vec<_>>();
+    /// assert_eq!(items, vec![(1, 'a'), (2, 'b')]);
+    /// ```
+    ///
+    fn into_iter(self) -> Self::IntoIter
+    {
+        IntoIter::new(self.root)
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a Tree<K, V>
+{
+    type Item     = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    /// Lets a borrowed tree be used directly in a `for` loop.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b')]);
+    /// let mut keys = vec![];
+    /// for (k, _) in &tree {
+    ///     keys.push(*k);
+    /// }
+    /// assert_eq!(keys, vec![1, 2]);
+    /// ```
+    ///
+    fn into_iter(self) -> Self::IntoIter
+    {
+        Iter::new(&self.root)
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut Tree<K, V>
+{
+    type Item     = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    /// Lets a mutably borrowed tree be used directly in a `for` loop, with
+    /// mutable access to its values.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = Tree::from_sorted_slice(&[(1, 10), (2, 20)]);
+    /// for (_, v) in &mut tree {
+    ///     *v *= 2;
+    /// }
+    /// assert_eq!(tree[&1], 20);
+    /// ```
+    ///
+    fn into_iter(self) -> Self::IntoIter
+    {
+        IterMut::new(&mut self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Verifies that every node's weight matches the size of its sub-tree and
+    /// that every node's balance is within the range `[-1, 1]`.
+    fn is_balanced<K: Clone + Ord, V: Clone>(tree: &Tree<K, V>) -> bool {
+        fn check<K: Clone + Ord, V: Clone>(link: &Link<K, V>) -> Option<isize> {
+            match link {
+                Filled(node) => {
+                    let wt = 1 + check(&node.left)? + check(&node.right)?;
+                    if wt == node.weight && node.balance().abs() <= 1 {
+                        Some(wt)
+                    } else {
+                        None
+                    }
+                },
+                Empty => Some(0),
+            }
+        }
+        check(&tree.root).is_some()
+    }
+
+    /// Returns the number of edges on the longest path from the root to a leaf.
+    fn max_depth<K: Clone + Ord, V: Clone>(tree: &Tree<K, V>) -> usize {
+        tree.root.edge_height()
+    }
+
+    #[test]
+    fn it_works() {
+        let mut tree = Tree::new();
+        let     data = "qwertyuiopasdfghjklzxcvbnmklasjfal;jasjfsa;";
+        for (i, ch) in data.chars().enumerate() {
+            tree.insert(ch, i);
+        }
+        assert_eq!(tree[&'q'], 0_usize);
+        assert_eq!(tree.get(&'w'), Some(&1_usize));
+    }
+    
+    #[test]
+    fn update_or_insert_and_update() {
+        let mut tree = Tree::new();
+
+        match tree.get_mut(&'b') {
+            Some(value) => *value += 7,
+            None => {
+                tree.insert('b', 7);
+            }
+        }
+        assert_eq!(tree[&'b'], 7);
+        
+        if let Some(value) = tree.get_mut(&'b') {
+            *value += 7;
+        }
+        assert_eq!(tree[&'b'], 14);
+        
+        tree[&'b'] += 7;
+        assert_eq!(tree[&'b'], 21);
+    }
+
+    #[test]
+    fn insert_sorted_batch() {
+        let mut tree = Tree::new();
+        tree.insert(0, 0);
+
+        for batch in 1..20 {
+            tree.insert_sorted_batch((0..batch).map(|i| (batch * 100 + i, i)));
+            assert!(is_balanced(&tree));
+        }
+        let mut tree = Tree::new();
+        for batch in 0..20 {
+            tree.insert_sorted_batch((0..batch).map(|i| (batch * 100 + i, i)));
+            assert!(is_balanced(&tree));
+        }
+        let mut n = 0;
+        for batch in 0..20 {
+            for i in 0..batch {
+                assert_eq!(tree.get_nth(n), Some((&(batch * 100 + i), &i)));
+                n += 1;
+            }
+        }
+        assert_eq!(tree.get_nth(n), None);
+    }
+
+    #[test]
+    fn equal_trees_hash_equally() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::HashSet;
+
+        fn hash_of(tree: &Tree<usize, char>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            tree.hash(&mut hasher);
+            hasher.finish()
+        }
+        let data = "abcdefghijklmnop".chars().enumerate().collect::<Vec<_>>();
+        let mut t1 = Tree::new();
+        let mut t2 = Tree::new();
+        let mut t3 = Tree::new();
+        for &(i, ch) in data.iter() {
+            t1.insert(i, ch);
+            t3.insert(i, ch);
+        }
+        for &(i, ch) in data.iter().rev() {
+            t2.insert(i, ch);
+        }
+        assert_eq!(t1, t2);
+        assert_eq!(hash_of(&t1), hash_of(&t2));
+
+        t3.insert(3, 'z');
+        assert_ne!(t1, t3);
+        assert_ne!(hash_of(&t1), hash_of(&t3));
+
+        let mut set = HashSet::new();
+        assert!( set.insert(t1));
+        assert!(!set.insert(t2));
+        assert!( set.insert(t3));
+    }
+
+    #[test]
+    fn approx_heap_bytes_grows_linearly() {
+        let mut tree = Tree::new();
+        let     node = std::mem::size_of::<Node<u32, u64>>();
+
+        for i in 0..100 {
+            assert_eq!(tree.approx_heap_bytes(), i as usize * node);
+            tree.insert(i, i as u64);
+        }
+        assert_eq!(tree.approx_heap_bytes(), 100 * node);
+    }
+
+    #[test]
+    fn pop_nth_ascending() {
+        let mut tree = Tree::new();
+        for (i, ch) in "qwertyuiopasdfghjklzxcvbnm".chars().enumerate() {
+            tree.insert(ch, i);
+        }
+        let mut prev = None;
+        for len in (0..26).rev() {
+            let (k, v) = tree.pop_nth(0).unwrap();
+            assert!(prev < Some(k));
+            assert_eq!(tree.len(), len);
+            assert_eq!(tree.get(&k), None);
+            assert!(tree.balance_histogram().is_some());
+            assert_eq!("qwertyuiopasdfghjklzxcvbnm".chars().nth(v), Some(k));
+            prev = Some(k);
+        }
+        assert_eq!(tree.pop_nth(0), None);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn iter_index_range_matches_skip_take() {
+        let mut tree = Tree::new();
+        for (i, ch) in "qwertyuiopasdfghjklzxcvbnm".chars().enumerate() {
+            tree.insert(ch, i);
+        }
+        for start in 0..30_usize {
+            for end in 0..30_usize {
+                let expected = tree.iter()
+                                   .skip(start)
+                                   .take(end.saturating_sub(start))
+                                   .collect::<Vec<_>>();
+                let actual   = tree.iter_index_range(start, end)
+                                   .collect::<Vec<_>>();
+                assert_eq!(actual, expected);
+            }
+        }
+        assert_eq!(Tree::<i32, i32>::new().iter_index_range(0, 5).count(), 0);
+    }
+
+    #[test]
+    fn subsets_and_supersets() {
+        let tree_of = |keys: &str| {
+            let mut tree = Tree::new();
+            for (i, ch) in keys.chars().enumerate() {
+                tree.insert(ch, i);
+            }
+            tree
+        };
+        let all   = tree_of("qwertyuiop");
+        let some  = tree_of("wtp");
+        let same  = tree_of("poiuytrewq");
+        let other = tree_of("asdfg");
+        let empty = tree_of("");
+
+        assert!( some.is_subset(&all));
+        assert!(!all.is_subset(&some));
+        assert!( all.is_superset(&some));
+        assert!(!some.is_superset(&all));
+
+        assert!(all.is_subset(&same));
+        assert!(all.is_superset(&same));
+
+        assert!(!other.is_subset(&all));
+        assert!(!other.is_superset(&all));
+        assert!(!all.is_subset(&other));
+
+        assert!(empty.is_subset(&all));
+        assert!(all.is_superset(&empty));
+        assert!(!tree_of("wtx").is_subset(&all));
+    }
+
+    #[test]
+    fn try_insert_absent_and_present() {
+        let mut tree = Tree::new();
+        for (i, ch) in "qwerty".chars().enumerate() {
+            assert_eq!(tree.try_insert(ch, i).map(|v| *v), Ok(i));
+        }
+        assert!(is_balanced(&tree));
+        assert_eq!(tree.len(), 6);
+
+        *tree.try_insert('a', 10).unwrap() += 1;
+        assert_eq!(tree[&'a'], 11);
+
+        match tree.try_insert('w', 99) {
+            Err(OccupiedError { key, existing, value }) => {
+                assert_eq!((key, *existing, value), ('w', 1, 99));
+                *existing = 100;
+            },
+            Ok(_) => panic!("Key 'w' should have been occupied."),
+        }
+        assert_eq!(tree[&'w'], 100);
+        assert_eq!(tree.len(), 7);
+    }
+
+    #[test]
+    fn try_insert_finds_value_after_rotations() {
+        let mut tree = Tree::new();
+        for i in 0..200 {
+            let key = i * 37 % 200;
+            *tree.try_insert(key, 0).unwrap() = key * 2;
+            assert!(is_balanced(&tree));
+        }
+        assert!(tree.iter().all(|(k, v)| *v == k * 2));
+    }
+
+    #[test]
+    fn root_key_value() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.root_key_value(), None);
+
+        for i in 0..7 {
+            tree.insert(i, i * 10);
+        }
+        let (k, v) = tree.root_key_value().unwrap();
+        assert_eq!(tree.get(k), Some(v));
+        
+        for i in 0..7 {
+            tree.remove(&i);
+        }
+        assert_eq!(tree.root_key_value(), None);
+    }
+
+    #[test]
+    fn clone_from_reuses_nodes() {
+        let mut src = Tree::new();
+        let mut dst = Tree::new();
+        for i in 0..100 {
+            src.insert(i, i.to_string());
+        }
+        for i in 50..60 {
+            dst.insert(i, String::new());
+        }
+        let root = match &dst.root {
+            Filled(node) => &**node as *const Node<_, _>,
+            Empty => panic!("Tree is empty."),
+        };
+        dst.clone_from(&src);
+        assert_eq!(dst, src);
+        assert!(is_balanced(&dst));
+        assert!(matches!(&dst.root,
+                         Filled(node) if std::ptr::eq(&**node, root)));
+
+        dst.clone_from(&Tree::new());
+        assert!(dst.is_empty());
+        dst.clone_from(&src);
+        assert_eq!(dst, src);
+
+        let copy = src.clone();
+        src.insert(1000, String::new());
+        assert_eq!(copy.len(), 100);
+        assert_eq!(src.len(), 101);
+    }
+
+    #[test]
+    fn first_and_last_value_mut() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.first_value_mut(), None);
+        assert_eq!(tree.last_value_mut(), None);
+
+        for i in (0..50).rev() {
+            tree.insert(i, i);
+        }
+        let before = tree.clone();
+        
+        *tree.first_value_mut().unwrap() = 100;
+        *tree.last_value_mut().unwrap() += 1;
+        assert_eq!(tree.first_key_value(), Some((&0, &100)));
+        assert_eq!(tree.last_key_value(), Some((&49, &50)));
+
+        let keys_before = before.iter().map(|(k, _)| k);
+        assert!(tree.iter().map(|(k, _)| k).eq(keys_before));
+        assert_eq!(tree.root_key_value().map(|(k, _)| k), 
+                   before.root_key_value().map(|(k, _)| k));
+    }
+
+    #[test]
+    fn remove_all_present_and_absent() {
+        let mut tree = Tree::new();
+        for i in 0..20 {
+            tree.insert(i, i * i);
+        }
+        let removed = tree.remove_all(vec![3, 5, 7, 25, 5, 30, 19, -1]);
+        assert_eq!(removed, 4);
+        assert_eq!(tree.len(), 16);
+        assert!(tree.balance_histogram().is_some());
+        
+        let remaining = tree.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        let expected  = (0..19).filter(|k| ![3, 5, 7].contains(k))
+                               .collect::<Vec<_>>();
+        assert_eq!(remaining, expected);
+        assert_eq!(tree.remove_all(Vec::new()), 0);
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.percentile(0.5), None);
+
+        for i in 0..101 {
+            tree.insert(i * 10, i);
+        }
+        assert_eq!(tree.percentile(0.0),  Some((&0,    &0  )));
+        assert_eq!(tree.percentile(1.0),  Some((&1000, &100)));
+        assert_eq!(tree.percentile(0.5),  Some((&500,  &50 )));
+        assert_eq!(tree.percentile(0.99), Some((&990,  &99 )));
+        assert_eq!(tree.percentile(-0.1), None);
+        assert_eq!(tree.percentile(1.1),  None);
+        assert_eq!(tree.percentile(f64::NAN), None);
+
+        tree.remove(&1000);
+        assert_eq!(tree.percentile(0.5),  Some((&500,  &50 )));
+    }
+
+    #[test]
+    fn from_sorted_slice_matches_inserts() {
+        for len in 0..100 {
+            let data = (0..len).map(|i| (i * 3, i.to_string()))
+                               .collect::<Vec<_>>();
+            let mut inserted = Tree::new();
+            for (k, v) in data.iter().cloned() {
+                inserted.insert(k, v);
+            }
+            let built = Tree::from_sorted_slice(&data);
+            assert_eq!(built, inserted);
+            assert_eq!(built.len(), len);
+            assert!(is_balanced(&built));
+        }
+    }
+
+    #[test]
+    fn swap_values() {
+        let mut tree = Tree::new();
+        for i in 0..30 {
+            tree.insert(i, i.to_string());
+        }
+        for (a, b) in [(0, 29), (3, 4), (15, 2), (7, 7), (28, 1)] {
+            let (va, vb) = (tree[&a].clone(), tree[&b].clone());
+            assert!(tree.swap_values(&a, &b));
+            assert_eq!((&tree[&a], &tree[&b]), (&vb, &va));
+        }
+        let before = tree.clone();
+        assert!(!tree.swap_values(&3, &30));
+        assert!(!tree.swap_values(&-1, &3));
+        assert!(!tree.swap_values(&40, &40));
+        assert_eq!(tree, before);
+    }
+
+    #[test]
+    fn trees_order_lexicographically() {
+        let tree_of = |keys: &[i32], rev: bool| {
+            let mut tree = Tree::new();
+            let mut keys = keys.to_vec();
+            if rev { 
+                keys.reverse(); 
+            }
+            for k in keys {
+                tree.insert(k, k * 2);
+            }
+            tree
+        };
+        let small = tree_of(&[1, 50, 60, 70, 80, 90], false);
+        let large = tree_of(&[2, 3], true);
+        let empty = tree_of(&[], false);
+        assert!(small < large);
+        assert!(empty < small);
+        assert_eq!(small.cmp(&tree_of(&[1, 50, 60, 70, 80, 90], true)), 
+                   Ordering::Equal);
+
+        let mut trees = vec![large.clone(), empty.clone(), small.clone()];
+        trees.sort();
+        assert_eq!(trees, vec![empty, small.clone(), large]);
+
+        let mut bigger_value = small.clone();
+        bigger_value[&90] += 1;
+        assert!(small < bigger_value);
+    }
+
+    #[test]
+    fn try_get_nth_reports_len() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.try_get_nth(0), Err(IndexOutOfRange { index: 0, 
+                                                              len:   0 }));
+        for i in 0..10 {
+            tree.insert(i, i);
+        }
+        for i in 0..10 {
+            assert_eq!(tree.try_get_nth(i), Ok((&i, &i)));
+        }
+        let err = tree.try_get_nth(10).unwrap_err();
+        assert_eq!((err.index, err.len), (10, 10));
+        
+        tree.remove(&0);
+        let err = tree.try_get_nth(usize::MAX).unwrap_err();
+        assert_eq!((err.index, err.len), (usize::MAX, 9));
+    }
+
+    #[test]
+    fn join_overlapping_and_disjoint() {
+        use JoinSide::*;
+
+        let mut evens = Tree::new();
+        let mut thirds = Tree::new();
+        for i in 0..10 {
+            evens.insert(i * 2, i);
+            thirds.insert(i * 3, i.to_string());
+        }
+        for (k, side) in evens.join(&thirds) {
+            match side {
+                Left(v)     => assert!(k % 2 == 0 && k % 3 != 0 && *v == k / 2),
+                Right(w)    => assert!((k % 2 != 0 || *k > 18) 
+                                           && *w == (k / 3).to_string()),
+                Both(v, w)  => assert!(k % 6 == 0 && *v == k / 2 
+                                                  && *w == (k / 3).to_string()),
+            }
+        }
+        let keys = evens.join(&thirds).map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys, vec![0, 2, 3, 4, 6, 8, 9, 10, 12, 14, 15, 16, 18, 
+                              21, 24, 27]);
+
+        let low  = Tree::from_sorted_slice(&[(1, ()), (2, ())]);
+        let high = Tree::from_sorted_slice(&[(3, ()), (4, ())]);
+        let sides = high.join(&low).map(|(_, s)| s).collect::<Vec<_>>();
+        assert_eq!(sides, vec![Right(&()), Right(&()), Left(&()), Left(&())]);
+        assert_eq!(low.join(&Tree::<i32, ()>::new()).count(), 2);
+    }
+
+    #[test]
+    fn rebalance_minimizes_height() {
+        let mut tree = Tree::new();
+        for i in 0..2000 {
+            let k = i * 7919 % 2000;
+            tree.insert(k, k * 2);
+        }
+        for i in (0..2000).filter(|i| i % 5 != 0) {
+            tree.remove(&i);
+        }
+        let before = tree.clone();
+        let n      = tree.len();
+        let target = (((n + 1) as f64).log2().ceil() as usize) - 1;
+        assert!(max_depth(&tree) > target);
+
+        tree.rebalance();
+        assert_eq!(max_depth(&tree), target);
+        assert_eq!(tree, before);
+        assert!(is_balanced(&tree));
+
+        let mut empty = Tree::<i32, i32>::new();
+        empty.rebalance();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn retain_mut_decrements_and_evicts() {
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, i % 4_u32);
+        }
+        tree.retain_mut(|_, ttl| {
+            *ttl = ttl.saturating_sub(1);
+            *ttl > 0
+        });
+        assert_eq!(tree.len(), 50);
+        assert!(is_balanced(&tree));
+        assert!(tree.iter().all(|(k, v)| *v == k % 4 - 1 && k % 4 >= 2));
+
+        tree.retain_mut(|_, _| false);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn fold_sums_values() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.fold(0, |sum, _, v| sum + v), 0);
+
+        for i in 0..500_u64 {
+            tree.insert(i * 7 % 500, i);
+        }
+        let sum = tree.fold(0, |sum, _, v| sum + v);
+        assert_eq!(sum, tree.iter().map(|(_, v)| v).sum::<u64>());
+
+        let keys = tree.fold(Vec::new(), |mut keys, k, _| { 
+            keys.push(*k); 
+            keys 
+        });
+        assert_eq!(keys, (0..500).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cursor_steps_both_ways() {
+        let mut tree = Tree::new();
+        for i in 0..50 {
+            tree.insert(i * 2, i);
+        }
+        let mut cursor = tree.cursor_at(&31);
+        for k in (32..100).step_by(2) {
+            assert_eq!(cursor.key_value(), Some((&k, &(k / 2))));
+            cursor.move_next();
+        }
+        assert_eq!(cursor.key_value(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.key_value(), Some((&98, &49)));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.key_value(), Some((&0, &0)));
+
+        let mut cursor = tree.cursor_at(&20);
+        for k in (0..=20).rev().step_by(2) {
+            assert_eq!(cursor.key_value(), Some((&k, &(k / 2))));
+            cursor.move_prev();
+        }
+        assert_eq!(cursor.key_value(), None);
+        cursor.move_next();
+        assert_eq!(cursor.key_value(), Some((&0, &0)));
+        
+        assert_eq!(tree.cursor_at(&-5).key_value(), Some((&0, &0)));
+        assert_eq!(tree.cursor_at(&99).key_value(), None);
+
+        let empty = Tree::<i32, i32>::new();
+        let mut cursor = empty.cursor_at(&0);
+        cursor.move_next();
+        cursor.move_prev();
+        assert_eq!(cursor.key_value(), None);
+    }
+
+    #[test]
+    fn get_or_default_present_and_absent() {
+        let mut tree: Tree<char, i32> = Tree::new();
+        assert_eq!(tree.get_or_default(&'a'), 0);
+
+        for (i, ch) in "qwerty".chars().enumerate() {
+            tree.insert(ch, -(i as i32) - 1);
+        }
+        assert_eq!(tree.get_or_default(&'q'), -1);
+        assert_eq!(tree.get_or_default(&'y'), -6);
+        assert_eq!(tree.get_or_default(&'z'), 0);
+        assert_eq!(tree.get(&'z'), None);
+        assert_eq!(tree.len(), 6);
+    }
+
+    #[test]
+    fn split_at_nth() {
+        let mut tree = Tree::new();
+        for i in 0..10 {
+            tree.insert(i, i * 10);
+        }
+        let rest = tree.split_at_nth(4);
+        assert_eq!(tree.len(), 4);
+        assert_eq!(rest.len(), 6);
+        assert!(tree.iter().map(|(k, _)| *k).eq(0..4));
+        assert!(rest.iter().map(|(k, _)| *k).eq(4..10));
+        assert!(is_balanced(&tree) && is_balanced(&rest));
+
+        let mut whole = tree.clone();
+        assert!(whole.split_at_nth(4).is_empty());
+        assert!(whole.split_at_nth(100).is_empty());
+        assert_eq!(whole, tree);
+        assert_eq!(whole.split_at_nth(0), tree);
+        assert!(whole.is_empty());
+
+        for len in 0..60 {
+            for index in 0..=len {
+                let data = (0..len).map(|i| (i, i)).collect::<Vec<_>>();
+                let mut tree = Tree::from_sorted_slice(&data);
+                let rest = tree.split_at_nth(index);
+                assert!(tree.iter().map(|(k, _)| *k).eq(0..index));
+                assert!(rest.iter().map(|(k, _)| *k).eq(index..len));
+                assert!(is_balanced(&tree) && is_balanced(&rest));
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_integer_keys() {
+        let mut tree = Tree::new();
+        let dist     = |a: &i64, b: &i64| (a - b).abs();
+        assert_eq!(tree.nearest(&5, dist), None);
+
+        for k in [-40, -3, 8, 15, 16, 100, 1000] {
+            tree.insert(k, k * 2);
+        }
+        assert_eq!(tree.nearest(&11, dist),    Some((&8,    &16  )));
+        assert_eq!(tree.nearest(&12, dist),    Some((&15,   &30  )));
+        assert_eq!(tree.nearest(&58, dist),    Some((&16,   &32  )));
+        assert_eq!(tree.nearest(&59, dist),    Some((&100,  &200 )));
+        assert_eq!(tree.nearest(&550, dist),   Some((&100,  &200 )));
+        assert_eq!(tree.nearest(&-100, dist),  Some((&-40,  &-80 )));
+        assert_eq!(tree.nearest(&5000, dist),  Some((&1000, &2000)));
+        assert_eq!(tree.nearest(&15, dist),    Some((&15,   &30  )));
+
+        for q in -50..1050 {
+            let best = tree.iter().map(|(k, _)| dist(&q, k)).min();
+            let got  = tree.nearest(&q, dist).map(|(k, _)| dist(&q, k));
+            assert_eq!(got, best);
+        }
+    }
+
+    #[test]
+    fn keys_where_value_exceeds() {
+        let mut tree = Tree::new();
+        for (i, ch) in "qwertyuiopasdfghjklzxcvbnm".chars().enumerate() {
+            tree.insert(ch, i);
+        }
+        let keys = tree.keys_where(|_, v| *v >= 20).collect::<Vec<_>>();
+        assert_eq!(keys, vec![&'b', &'c', &'m', &'n', &'v', &'x']);
+
+        let threshold = 100;
+        assert_eq!(tree.keys_where(move |_, v| *v > threshold).count(), 0);
+        assert_eq!(tree.keys_where(|k, _| k.is_alphabetic()).count(), 26);
+    }
+
+    #[test]
+    fn coalesce_equal_value_runs() {
+        let mut tree = Tree::new();
+        for (k, v) in [(10, 'x'), (20, 'y'), (30, 'y'), (40, 'y'), (50, 'x'),
+                       (60, 'z'), (70, 'z')] {
+            tree.insert(k, v);
+        }
+        tree.coalesce(|_, last| *last);
+        let items = tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        assert_eq!(items, vec![(10, 'x'), (40, 'y'), (50, 'x'), (70, 'z')]);
+        assert!(is_balanced(&tree));
+
+        let mut empty = Tree::<i32, i32>::new();
+        empty.coalesce(|k, _| *k);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn iter_from_resumes_mid_tree() {
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i * 2, i);
+        }
+        let keys = tree.iter_from(&50).map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys, (25..100).map(|i| i * 2).collect::<Vec<_>>());
+        
+        let keys = tree.iter_from(&51).map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys, (26..100).map(|i| i * 2).collect::<Vec<_>>());
+
+        for start in -2..202 {
+            let expected = tree.iter().filter(|(k, _)| **k >= start);
+            assert!(tree.iter_from(&start).eq(expected));
+        }
+        assert_eq!(tree.iter_from(&199).count(), 0);
+        assert_eq!(tree.iter_from(&-5).count(), 100);
+    }
+
+    #[test]
+    fn take_without_clone() {
+        #[derive(Debug, PartialEq)]
+        struct NoClone(u32);
+
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, NoClone(i * 10));
+        }
+        for i in (0..100).step_by(3) {
+            assert_eq!(tree.take(&i), Some(NoClone(i * 10)));
+        }
+        assert_eq!(tree.take(&3), None);
+        assert_eq!(tree.take(&1000), None);
+        for i in (0..100).filter(|i| i % 3 != 0) {
+            assert_eq!(tree.take(&i), Some(NoClone(i * 10)));
+        }
+        assert!(!tree.root.is_filled());
+    }
+
+    #[test]
+    fn find_key_by_value() {
+        let mut tree = Tree::new();
+        for i in 0..50 {
+            tree.insert(i, i % 7);
+        }
+        assert!(tree.contains_value(&6));
+        assert_eq!(tree.find_key(&6), Some(&6));
+        assert_eq!(tree.find_key(&0), Some(&0));
+        tree.remove(&0);
+        assert_eq!(tree.find_key(&0), Some(&7));
+
+        assert!(!tree.contains_value(&7));
+        assert_eq!(tree.find_key(&7), None);
+    }
+
+    #[test]
+    fn split_first_and_last_peel_in_order() {
+        fn peel_first(tree: Tree<i32, i32>, out: &mut Vec<i32>) {
+            if let Some(((k, v), rest)) = tree.split_first() {
+                assert_eq!(v, k * 2);
+                out.push(k);
+                peel_first(rest, out);
+            }
+        }
+        let mut tree = Tree::new();
+        for i in (0..200).rev() {
+            tree.insert(i, i * 2);
+        }
+        let mut keys = Vec::new();
+        peel_first(tree.clone(), &mut keys);
+        assert_eq!(keys, (0..200).collect::<Vec<_>>());
+
+        let mut keys = Vec::new();
+        let mut rest = tree;
+        while let Some(((k, _), r)) = rest.split_last() {
+            assert!(is_balanced(&r));
+            keys.push(k);
+            rest = r;
+        }
+        assert_eq!(keys, (0..200).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn balance_factor_of_root_and_leaf() {
+        let items = (1..=7).map(|i| (i, i)).collect::<Vec<_>>();
+        let mut tree = Tree::from_sorted_slice(&items);
+        assert_eq!(tree.root_key_value(), Some((&4, &4)));
+        assert_eq!(tree.balance_factor(&4), Some(0));
+        assert_eq!(tree.balance_factor(&1), Some(0));
+
+        tree.insert(8, 8);
+        tree.insert(9, 9);
+        assert_eq!(tree.root_key_value(), Some((&4, &4)));
+        assert_eq!(tree.balance_factor(&4), Some(-1));
+        assert_eq!(tree.balance_factor(&9), Some(0));
+        assert_eq!(tree.balance_factor(&10), None);
+    }
+
+    #[test]
+    fn bulk_insert_matches_one_by_one() {
+        let existing = (0..300).map(|i| (i * 7 % 300, 0)).collect::<Vec<_>>();
+        let batch    = (0..500).map(|i| (i * 13 % 400, i)).collect::<Vec<_>>();
+
+        let mut one = Tree::new();
+        let mut all = Tree::new();
+        for (k, v) in existing {
+            one.insert(k, v);
+            all.insert(k, v);
+        }
+        for &(k, v) in &batch {
+            one.insert(k, v);
+        }
+        all.bulk_insert(batch);
+
+        assert_eq!(all, one);
+        assert_eq!(all.len(), 400);
+        assert_eq!(all.get(&13), Some(&401));
+        assert!(is_balanced(&all));
+    }
+
+    #[test]
+    fn get_path_follows_the_search() {
+        let mut tree = Tree::new();
+        for i in 0..1000 {
+            tree.insert(i, ());
+        }
+        let root  = tree.root_key_value().map(|(k, _)| k);
+        let depth = max_depth(&tree);
+        for i in (0..1000).step_by(37) {
+            let path = tree.get_path(&i).unwrap();
+            assert_eq!(path.first().copied(), root);
+            assert_eq!(path.last().copied(), Some(&i));
+            assert!(path.len() <= depth + 1);
+        }
+        let longest = (0..1000).map(|i| tree.get_path(&i).unwrap().len());
+        assert_eq!(longest.max(), Some(depth + 1));
+        assert_eq!(tree.get_path(&1000), None);
+    }
+
+    #[test]
+    fn for_loops_over_borrowed_trees() {
+        let mut tree = Tree::new();
+        for i in (0..100).rev() {
+            tree.insert(i, i);
+        }
+        for (k, v) in &mut tree {
+            *v += k * 100;
+        }
+        let mut expected = 0;
+        for (k, v) in &tree {
+            assert_eq!(*k, expected);
+            assert_eq!(*v, k * 101);
+            expected += 1;
+        }
+        assert_eq!(expected, 100);
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn rotation_count_sorted_versus_balanced() {
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, ());
+        }
+        let sorted = tree.rotation_count();
+        assert!(sorted > 0);
+
+        let rebuilt = tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        let rebuilt = Tree::from_sorted_slice(&rebuilt);
+        assert_eq!(rebuilt.rotation_count(), 0);
+
+        let mut level_order = Tree::new();
+        for &i in &[4, 2, 6, 1, 3, 5, 7] {
+            level_order.insert(i, ());
+        }
+        assert_eq!(level_order.rotation_count(), 0);
+
+        tree.clear();
+        assert_eq!(tree.rotation_count(), 0);
+        tree.insert(0, ());
+        assert_eq!(tree.rotation_count(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn rotation_count_survives_removals() {
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, ());
+        }
+        let mut count = tree.rotation_count();
+        for i in 0..49 {
+            assert_eq!(tree.take(&i), Some(()));
+            assert!(tree.rotation_count() >= count);
+            count = tree.rotation_count();
+
+            assert_eq!(tree.remove(&(99 - i)), Some(()));
+            assert!(tree.rotation_count() >= count);
+            count = tree.rotation_count();
+        }
+        assert!(count > 0);
+        assert_eq!(tree.len(), 2);
+
+        tree.remove(&49);
+        tree.remove(&50);
+        assert!(tree.is_empty());
+        assert_eq!(tree.rotation_count(), count);
+    }
+
+    #[test]
+    fn min_max_matches_first_and_last() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.min_max(), None);
+
+        tree.insert(5, 'e');
+        assert_eq!(tree.min_max(), Some(((&5, &'e'), (&5, &'e'))));
+
+        for (i, ch) in "zyxwvu".chars().enumerate() {
+            tree.insert(i * 3, ch);
+        }
+        let (min, max) = tree.min_max().unwrap();
+        assert_eq!(Some(min), tree.first_key_value());
+        assert_eq!(Some(max), tree.last_key_value());
+        assert_eq!(min, (&0, &'z'));
+        assert_eq!(max, (&15, &'u'));
+    }
+
+    #[test]
+    fn truncate_to_smallest_keeps_the_head() {
+        let mut tree = Tree::new();
+        for i in (0..20).rev() {
+            tree.insert(i, i * i);
+        }
+        tree.truncate_to_smallest(20);
+        assert_eq!(tree.len(), 20);
+
+        tree.truncate_to_smallest(5);
+        let items = tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        assert_eq!(items, vec![(0, 0), (1, 1), (2, 4), (3, 9), (4, 16)]);
+        assert!(is_balanced(&tree));
+
+        tree.truncate_to_smallest(0);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn into_values_in_key_order() {
+        #[derive(Debug, PartialEq)]
+        struct NoClone(i32);
+
+        let mut tree = Tree::new();
+        for i in [5, 3, 9, 1, 7, 2, 8, 4, 6, 0] {
+            tree.insert(i, NoClone(-i));
+        }
+        let values = tree.into_values().collect::<Vec<_>>();
+        assert_eq!(values, (0..10).map(|i| NoClone(-i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn into_keys_sorted() {
+        struct NoClone;
+
+        let mut tree = Tree::new();
+        for i in 0..200 {
+            tree.insert((i * 71) % 200, NoClone);
+        }
+        let keys = tree.into_keys().collect::<Vec<_>>();
+        assert_eq!(keys, (0..200).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn range_values_mut_only_touches_the_range() {
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, i);
+        }
+        for v in tree.range_values_mut(20..=40) {
+            *v *= 3;
+        }
+        let tail = (Bound::Excluded(90), Bound::Unbounded);
+        for v in tree.range_values_mut(tail) {
+            *v = 0;
+        }
+        assert_eq!(tree.range_values_mut(50..50).count(), 0);
+        assert_eq!(tree.range_values_mut(..10).count(), 10);
+
+        for (k, v) in tree.iter() {
+            match k {
+                20..=40 => assert_eq!(*v, k * 3),
+                91..=99 => assert_eq!(*v, 0),
+                _       => assert_eq!(v, k),
+            }
+        }
+    }
+
+    #[test]
+    fn height_report_of_known_trees() {
+        let empty: Tree<i32, ()> = Tree::new();
+        assert_eq!(empty.height_report(), (0, 0));
+
+        let items = (0..15).map(|i| (i, ())).collect::<Vec<_>>();
+        let mut tree = Tree::from_sorted_slice(&items);
+        assert_eq!(tree.height_report(), (3, 3));
+
+        tree.insert(15, ());
+        assert_eq!(tree.height_report(), (4, 4));
+        assert_eq!(tree.height_report().0, max_depth(&tree));
+
+        let mut chain = Tree::new();
+        for i in 0..8 {
+            chain.insert(i, ());
+        }
+        let (actual, optimal) = chain.height_report();
+        assert_eq!(optimal, 3);
+        assert_eq!(actual, max_depth(&chain));
+    }
+
+    #[test]
+    fn insert_max_keeps_best_scores() {
+        let mut best = Tree::new();
+        let rounds = [("ann", 5), ("bob", 9), ("ann", 3), ("bob", 9),
+                      ("ann", 8), ("cat", 1), ("bob", 4)];
+        let changed = rounds.iter()
+                            .map(|&(k, v)| best.insert_max(k, v))
+                            .collect::<Vec<_>>();
+        assert_eq!(changed, [true, true, false, false, true, true, false]);
+        assert_eq!(best[&"ann"], 8);
+        assert_eq!(best[&"bob"], 9);
+        assert_eq!(best[&"cat"], 1);
+    }
+
+    #[test]
+    fn key_pairs_find_gaps() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.key_pairs().count(), 0);
+        tree.insert(1, ());
+        assert_eq!(tree.key_pairs().count(), 0);
+
+        for k in [2, 5, 6, 10] {
+            tree.insert(k, ());
+        }
+        let gaps = tree.key_pairs()
+                       .filter(|(a, b)| *b - *a > 1)
+                       .collect::<Vec<_>>();
+        assert_eq!(gaps, [(&2, &5), (&6, &10)]);
+    }
+
+    #[test]
+    fn clone_subtree_of_a_known_tree() {
+        let items = (1..=15).map(|i| (i, i * 10)).collect::<Vec<_>>();
+        let tree  = Tree::from_sorted_slice(&items);
+
+        let whole = tree.clone_subtree(&8).unwrap();
+        assert_eq!(whole, tree);
+
+        let mut left = tree.clone_subtree(&4).unwrap();
+        let keys = left.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys, (1..=7).collect::<Vec<_>>());
+        assert_eq!(left.len(), 7);
+        assert!(is_balanced(&left));
+
+        let leaf = tree.clone_subtree(&13).unwrap();
+        assert_eq!(leaf.iter().collect::<Vec<_>>(), [(&13, &130)]);
+        assert!(tree.clone_subtree(&16).is_none());
+
+        left.insert(0, 0);
+        assert_eq!(tree.len(), 15);
+        assert!(!tree.contains_value(&0));
+    }
+
+    #[test]
+    fn merge_sorted_joins_disjoint_trees() {
+        let small = (0..10).map(|i| (i, i)).collect::<Vec<_>>();
+        let large = (10..500).map(|i| (i, i)).collect::<Vec<_>>();
+
+        let mut tree = Tree::from_sorted_slice(&small);
+        tree.merge_sorted(Tree::from_sorted_slice(&large));
+        assert!(tree.iter().map(|(k, _)| *k).eq(0..500));
+        assert!(is_balanced(&tree));
+
+        let mut tree = Tree::from_sorted_slice(&large);
+        tree.merge_sorted(Tree::from_sorted_slice(&small));
+        assert!(tree.iter().map(|(k, _)| *k).eq(0..500));
+        assert!(is_balanced(&tree));
+
+        tree.merge_sorted(Tree::new());
+        assert_eq!(tree.len(), 500);
+
+        let mut empty = Tree::new();
+        empty.merge_sorted(tree.clone());
+        assert_eq!(empty, tree);
+    }
+
+    #[test]
+    fn min_and_max_by_value() {
+        let mut prices: Tree<&str, u32> = Tree::new();
+        assert_eq!(prices.min_by_value(), None);
+        assert_eq!(prices.max_by_value(), None);
+
+        for (item, price) in [("pear", 4), ("apple", 2), ("fig", 9),
+                              ("kiwi", 2), ("date", 9), ("plum", 5)] {
+            prices.insert(item, price);
+        }
+        assert_eq!(prices.min_by_value(), Some((&"apple", &2)));
+        assert_eq!(prices.max_by_value(), Some((&"date", &9)));
+
+        prices.remove(&"apple");
+        prices.remove(&"date");
+        assert_eq!(prices.min_by_value(), Some((&"kiwi", &2)));
+        assert_eq!(prices.max_by_value(), Some((&"fig", &9)));
+    }
+
+    #[test]
+    fn extract_if_odd_keys() {
+        let mut tree = Tree::new();
+        for i in (0..101).rev() {
+            tree.insert(i, i.to_string());
+        }
+        let odd = tree.extract_if(|k, _| k % 2 == 1).collect::<Vec<_>>();
+        assert_eq!(odd.len(), 50);
+        assert!(odd.iter().map(|(k, _)| *k).eq((1..101).step_by(2)));
+        assert!(odd.iter().all(|(k, v)| k.to_string() == *v));
+
+        assert_eq!(tree.len(), 51);
+        assert!(tree.iter().map(|(k, _)| *k).eq((0..101).step_by(2)));
+        assert!(is_balanced(&tree));
+
+        assert_eq!(tree.extract_if(|_, _| false).count(), 0);
+        assert_eq!(tree.len(), 51);
+    }
+
+    #[test]
+    fn replace_existing_and_absent() {
+        let mut tree = Tree::new();
+        for i in 0..10 {
+            tree.insert(i, vec![i]);
+        }
+        assert_eq!(tree.replace(&4, vec![40, 41]), Ok(vec![4]));
+        assert_eq!(tree[&4], vec![40, 41]);
+
+        assert_eq!(tree.replace(&10, vec![100]), Err(vec![100]));
+        assert_eq!(tree.get(&10), None);
+        assert_eq!(tree.len(), 10);
+    }
+
+    #[test]
+    fn count_less_and_greater_match_scans() {
+        let mut tree = Tree::new();
+        for i in 0..300 {
+            tree.insert((i * 11) % 600, ());
+        }
+        for q in -1..602 {
+            let less    = tree.iter().filter(|(k, _)| **k < q).count();
+            let greater = tree.iter().filter(|(k, _)| **k > q).count();
+            assert_eq!(tree.count_less(&q), less);
+            assert_eq!(tree.count_greater(&q), greater);
+        }
+    }
+
+    #[test]
+    fn with_sorted_keys_is_optimal() {
+        let tree = Tree::with_sorted_keys(0..1000, 0_u8);
+        assert_eq!(tree.len(), 1000);
+        assert!(tree.iter().map(|(k, _)| *k).eq(0..1000));
+        assert!(tree.iter().all(|(_, v)| *v == 0));
+
+        let (actual, optimal) = tree.height_report();
+        assert_eq!(optimal, 9);
+        assert_eq!(actual, optimal);
+        assert!(is_balanced(&tree));
+    }
+
+    #[test]
+    fn into_reversed_iterates_descending() {
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, i * 2);
+        }
+        let mut rev = tree.into_reversed();
+        assert!(rev.iter().map(|(k, _)| k.0).eq((0..100).rev()));
+        assert!(rev.iter().all(|(k, v)| *v == k.0 * 2));
+        assert!(is_balanced(&rev));
+
+        rev.insert(Reverse(1000), 0);
+        assert_eq!(rev.first_key_value(), Some((&Reverse(1000), &0)));
+    }
+
+    #[test]
+    fn keys_page_clamps_at_the_end() {
+        let mut tree = Tree::new();
+        for i in (0..10).rev() {
+            tree.insert(i * 10, ());
+        }
+        assert_eq!(tree.keys_page(5, 3), [&50, &60, &70]);
+        assert_eq!(tree.keys_page(8, 3), [&80, &90]);
+        assert_eq!(tree.keys_page(0, 0), Vec::<&i32>::new());
+        assert!(tree.keys_page(10, 3).is_empty());
+        assert!(tree.keys_page(usize::MAX, 3).is_empty());
+    }
+
+    #[test]
+    fn apply_mixed_ops() {
+        let mut tree = Tree::with_sorted_keys(0..10, 0);
+        let mut ops  = Vec::new();
+        for i in 5..15 {
+            ops.push(Op::Insert(i, i));
+        }
+        for i in (0..20).step_by(3) {
+            ops.push(Op::Remove(i));
+        }
+        ops.push(Op::Insert(0, -1));
+
+        let stats = tree.apply(ops);
+        assert_eq!(stats, ApplyStats { inserted: 6, updated: 5, removed: 5 });
+        assert!(tree.balance_histogram().is_some());
+
+        let expected = [(0, -1), (1, 0), (2, 0), (4, 0), (5, 5), (7, 7),
+                        (8, 8), (10, 10), (11, 11), (13, 13), (14, 14)];
+        assert!(tree.iter().map(|(k, v)| (*k, *v)).eq(expected));
+    }
+
+    #[test]
+    fn verify_weights_detects_corruption() {
+        let mut tree = Tree::new();
+        for i in 0..200 {
+            tree.insert((i * 31) % 200, ());
+        }
+        for i in (0..200).step_by(7) {
+            tree.remove(&i);
+        }
+        assert!(tree.verify_weights());
+        assert!(Tree::<i32, ()>::new().verify_weights());
+
+        if let Filled(node) = &mut tree.root {
+            if let Filled(left) = &mut node.left {
+                if let Filled(leaf) = &mut left.right {
+                    leaf.weight += 1;
+                }
+            }
+        }
+        assert!(!tree.verify_weights());
+    }
+
+    #[test]
+    fn rotations_keep_weights_on_deep_subtrees() {
+        let items = (0..127).map(|i| (i, ())).collect::<Vec<_>>();
+        type Rotation = fn(&mut Link<i32, ()>, &mut Stats);
+        let rotations: [Rotation; 4] = [
+            Link::rotate_left_left,
+            Link::rotate_right_right,
+            Link::rotate_left_right,
+            Link::rotate_right_left,
+        ];
+        for rotate in rotations.iter() {
+            let mut tree  = Tree::from_sorted_slice(&items);
+            let mut stats = Stats::default();
+            rotate(&mut tree.root, &mut stats);
+            assert!(tree.verify_weights());
+            if let Filled(node) = &mut tree.root {
+                rotate(&mut node.left, &mut stats);
+                rotate(&mut node.right, &mut stats);
+            }
+            assert!(tree.verify_weights());
+            assert!(tree.iter().map(|(k, _)| *k).eq(0..127));
+            assert_eq!(tree.len(), 127);
+        }
+    }
+
+    #[test]
+    fn get_or_try_insert_with_failure_leaves_tree() {
+        let mut tree: Tree<&str, String> = Tree::new();
+        let mut calls = 0;
+
+        let err = tree.get_or_try_insert_with("cfg", || {
+            calls += 1;
+            Err("missing file")
+        });
+        assert_eq!(err, Err("missing file"));
+        assert!(tree.get(&"cfg").is_none());
+        assert!(tree.is_empty());
+
+        let ok = tree.get_or_try_insert_with("cfg", || {
+            calls += 1;
+            Ok::<_, &str>("loaded".to_string())
+        });
+        ok.unwrap().push('!');
+
+        let again = tree.get_or_try_insert_with("cfg", || {
+            calls += 1;
+            Err("unused")
+        });
+        assert_eq!(again.map(|v| v.clone()), Ok("loaded!".to_string()));
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn retain_top_three_by_value() {
+        let mut board = Tree::new();
+        let scores = [("amy", 40), ("ben", 75), ("cal", 12), ("dee", 75),
+                      ("eve", 90), ("fay", 33), ("gus", 75), ("hal", 5),
+                      ("ivy", 61), ("jon", 20)];
+        for (name, score) in scores {
+            board.insert(name, score);
+        }
+        board.retain_top_n_by_value(3);
+        let left = board.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        assert_eq!(left, [("ben", 75), ("dee", 75), ("eve", 90)]);
+        assert!(is_balanced(&board));
+
+        board.retain_top_n_by_value(10);
+        assert_eq!(board.len(), 3);
+        board.retain_top_n_by_value(0);
+        assert!(board.is_empty());
+    }
+
+    #[test]
+    fn diff_of_two_snapshots() {
+        let before = Tree::with_sorted_keys(0..20, 0);
+        let mut after = before.clone();
+        after.remove(&0);
+        after.remove(&13);
+        after.insert(7, 1);
+        after.insert(12, 0);
+        after.insert(25, 2);
+        after.insert(-3, 3);
+
+        let changes = before.diff(&after).collect::<Vec<_>>();
+        assert_eq!(changes, [Change::Added(&-3, &3),
+                             Change::Removed(&0, &0),
+                             Change::Modified { key: &7, old: &0, new: &1 },
+                             Change::Removed(&13, &0),
+                             Change::Added(&25, &2)]);
+
+        assert_eq!(before.diff(&before).count(), 0);
+        assert_eq!(after.diff(&before).count(), changes.len());
+    }
+
+    #[test]
+    fn collect_keys_into_short_and_long_buffers() {
+        let tree = Tree::with_sorted_keys((0..50).map(|i| i * 2), ());
+
+        let mut short = [None; 4];
+        assert_eq!(tree.collect_keys_into(&mut short), 4);
+        assert_eq!(short, [Some(&0), Some(&2), Some(&4), Some(&6)]);
+
+        let mut long = [None; 64];
+        assert_eq!(tree.collect_keys_into(&mut long), 50);
+        assert_eq!(long[49], Some(&98));
+        assert_eq!(long[50], None);
+
+        assert_eq!(tree.collect_keys_into(&mut []), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn rotations_since_rebalance_resets() {
+        let mut tree = Tree::new();
+        for i in 0..64 {
+            tree.insert(i, ());
+        }
+        let total = tree.rotation_count();
+        assert!(total > 0);
+        assert_eq!(tree.rotations_since_rebalance(), total);
+
+        tree.rebalance();
+        assert_eq!(tree.rotations_since_rebalance(), 0);
+        assert_eq!(tree.rotation_count(), total);
+
+        for i in 64..128 {
+            tree.insert(i, ());
+        }
+        let since = tree.rotations_since_rebalance();
+        assert!(since > 0);
+        assert_eq!(tree.rotation_count(), total + since);
+
+        for i in 0..96 {
+            let root = *tree.root_key_value().unwrap().0;
+            if i % 2 == 0 {
+                tree.take(&root);
+            } else {
+                tree.remove(&root);
+            }
+        }
+        let since = tree.rotations_since_rebalance();
+        assert_eq!(tree.len(), 32);
+        assert_eq!(tree.rotation_count(), total + since);
+    }
+
+    #[test]
+    fn get_nth_from_end_mirrors_get_nth() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.get_nth_from_end(0), None);
+        for i in 0..40 {
+            tree.insert(i, i * i);
+        }
+        assert_eq!(tree.get_nth_from_end(0), tree.last_key_value());
+        for n in 0..40 {
+            assert_eq!(tree.get_nth_from_end(n), tree.get_nth(39 - n));
+        }
+        assert_eq!(tree.get_nth_from_end(40), None);
+        assert_eq!(tree.get_nth_from_end(usize::MAX), None);
+    }
+
+    #[test]
+    fn range_is_empty_for_overlaps_and_gaps() {
+        let tree = Tree::with_sorted_keys((0..100).map(|i| i * 10), ());
+
+        assert!(!tree.range_is_empty(15..25));
+        assert!(!tree.range_is_empty(..));
+        assert!(!tree.range_is_empty(..=0));
+        assert!(!tree.range_is_empty(990..));
+        let (above, upto) = (Bound::Excluded(500), Bound::Included(510));
+        assert!(!tree.range_is_empty((above, upto)));
+
+        assert!(tree.range_is_empty(11..20));
+        assert!(tree.range_is_empty(..0));
+        assert!(tree.range_is_empty(991..));
+        let (above, below) = (Bound::Excluded(500), Bound::Excluded(510));
+        assert!(tree.range_is_empty((above, below)));
+        assert!(Tree::<i32, ()>::new().range_is_empty(..));
+    }
+
+    #[test]
+    fn get_unchecked_nth_matches_get_nth() {
+        fn checked(tree: &Tree<u32, u32>, i: usize) -> Option<(&u32, &u32)> {
+            if i < tree.len() {
+                // SAFETY: `i` was just checked against the length.
+                Some(unsafe { tree.get_unchecked_nth(i) })
+            } else {
+                None
+            }
+        }
+        let mut tree = Tree::new();
+        for i in 0..300 {
+            tree.insert((i * 7) % 300, i);
+        }
+        for i in 0..310 {
+            assert_eq!(checked(&tree, i), tree.get_nth(i));
+        }
+    }
+
+    #[test]
+    fn balance_histogram_of_known_trees() {
+        let tree = Tree::with_sorted_keys(0..15, ());
+        assert_eq!(tree.balance_histogram(), Some([0, 15, 0]));
+
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, ());
+        }
+        let counts = tree.balance_histogram().unwrap();
+        assert_eq!(counts.iter().sum::<usize>(), tree.len());
+        assert!(counts[0] > 0);
+
+        if let Filled(node) = &mut tree.root {
+            node.left = Empty;
+            node.weight = 1 + node.right.weight_or_zero();
+        }
+        assert_eq!(tree.balance_histogram(), None);
+    }
+
+    #[test]
+    fn get_pair_mut_combines_values() {
+        let mut accounts = Tree::new();
+        for (name, balance) in [("ann", 100), ("bob", 50), ("cat", 0)] {
+            accounts.insert(name, balance);
+        }
+        if let Some((from, to)) = accounts.get_pair_mut(&"ann", &"cat") {
+            *from -= 30;
+            *to   += 30;
+        }
+        let (b, a) = accounts.get_pair_mut(&"bob", &"ann").unwrap();
+        std::mem::swap(a, b);
+
+        assert_eq!(accounts[&"ann"], 50);
+        assert_eq!(accounts[&"bob"], 70);
+        assert_eq!(accounts[&"cat"], 30);
+
+        assert!(accounts.get_pair_mut(&"bob", &"bob").is_none());
+        assert!(accounts.get_pair_mut(&"bob", &"dan").is_none());
+        assert!(accounts.get_pair_mut(&"eve", &"ann").is_none());
+    }
+
+    #[test]
+    fn filter_map_values_thresholds() {
+        let mut readings = Tree::new();
+        for i in 0..100 {
+            readings.insert(i, (i * 37) % 100);
+        }
+        let expected = readings.iter()
+                               .filter(|(_, v)| **v >= 50)
+                               .map(|(k, v)| (*k, format!("{}%", v)))
+                               .collect::<Vec<_>>();
+
+        let high = readings.filter_map_values(|_, v| {
+            if v >= 50 { Some(format!("{}%", v)) } else { None }
+        });
+        assert_eq!(high.len(), 50);
+        assert!(high.iter().map(|(k, v)| (*k, v.clone())).eq(expected));
+        assert!(is_balanced(&high));
+    }
+
+    #[test]
+    fn try_from_pairs_clean_and_duplicate() {
+        let pairs = (0..50).map(|i| ((i * 13) % 50, i)).collect::<Vec<_>>();
+        let tree  = Tree::try_from_pairs(pairs).unwrap();
+        assert_eq!(tree.len(), 50);
+        assert_eq!(tree[&13], 1);
+
+        let pairs = vec![("a", 1), ("b", 2), ("c", 3), ("b", 4), ("a", 5)];
+        let err   = Tree::try_from_pairs(pairs).unwrap_err();
+        assert_eq!(err, DuplicateKey { key: "b" });
+
+        let empty = Tree::<u8, u8>::try_from_pairs(vec![]).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn split_while_below_fifty() {
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert((i * 57) % 100, i);
+        }
+        let rest = tree.split_while(|k| *k < 50);
+        assert!(tree.iter().map(|(k, _)| *k).eq(0..50));
+        assert!(rest.iter().map(|(k, _)| *k).eq(50..100));
+        assert!(is_balanced(&tree) && is_balanced(&rest));
+
+        let mut all = rest.clone();
+        assert!(all.split_while(|_| true).is_empty());
+        assert_eq!(all, rest);
+
+        let none = all.split_while(|_| false);
+        assert!(all.is_empty());
+        assert_eq!(none, rest);
+    }
+
+    #[test]
+    fn iter_reports_exact_len() {
+        let mut tree = Tree::new();
+        for i in (0..64).rev() {
+            tree.insert(i, ());
+        }
+        let mut iter = tree.iter();
+        assert_eq!(iter.len(), tree.len());
+        for left in (0..64).rev() {
+            assert!(iter.next().is_some());
+            assert_eq!(iter.len(), left);
+        }
+        assert!(iter.next().is_none());
+        assert_eq!(iter.len(), 0);
+
+        assert_eq!(Iter::new_at_nth(&tree.root, 60).len(), 4);
+        assert_eq!(Iter::new_at_nth(&tree.root, 99).len(), 0);
+        for k in -1..66 {
+            let iter = Iter::new_at_key(&tree.root, &k);
+            assert_eq!(iter.len(), tree.count_greater(&(k - 1)));
+            assert_eq!(iter.count(), tree.count_greater(&(k - 1)));
+        }
+    }
+
+    #[test]
+    fn insert_observed_fires_only_on_overwrite() {
+        let mut tree = Tree::new();
+        let mut audit = Vec::new();
+        for (k, v) in [(1, 'a'), (2, 'b'), (1, 'c'), (3, 'd'), (1, 'e')] {
+            tree.insert_observed(k, v, |k, old, new| {
+                audit.push((*k, *old, *new));
+            });
+        }
+        assert_eq!(audit, [(1, 'a', 'c'), (1, 'c', 'e')]);
+        assert_eq!(tree.insert_observed(2, 'z', |_, _, _| {}), Some('b'));
+        assert_eq!(tree.insert_observed(4, 'z', |_, _, _| panic!()), None);
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree[&1], 'e');
+    }
+
+    #[test]
+    fn subtree_height_of_fixed_shape() {
+        let mut tree = Tree::with_sorted_keys(1..=15, ());
+        assert_eq!(tree.subtree_height(&8), Some(3));
+        assert_eq!(tree.subtree_height(&4), Some(2));
+        assert_eq!(tree.subtree_height(&12), Some(2));
+        assert_eq!(tree.subtree_height(&6), Some(1));
+        assert_eq!(tree.subtree_height(&7), Some(0));
+
+        tree.insert(16, ());
+        assert_eq!(tree.subtree_height(&15), Some(1));
+        assert_eq!(tree.subtree_height(&14), Some(2));
+        assert_eq!(tree.subtree_height(&8), Some(4));
+        assert_eq!(tree.subtree_height(&0), None);
+    }
+
+    #[test]
+    fn take_all_empties_the_tree() {
+        let mut tree = Tree::new();
+        for i in (0..30).rev() {
+            tree.insert(i.to_string(), i);
+        }
+        let pairs = tree.take_all();
+        assert_eq!(pairs.len(), 30);
+        assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(pairs.iter().all(|(k, v)| *k == v.to_string()));
+        assert!(tree.is_empty());
+        assert!(tree.take_all().is_empty());
+
+        tree.insert("again".to_string(), 0);
+        assert_eq!(tree.len(), 1);
+    }
+
+    #[test]
+    fn distinct_value_count_with_repeats() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.distinct_value_count(), 0);
+        for i in 0..100 {
+            tree.insert(i, (i % 7).to_string());
+        }
+        assert_eq!(tree.distinct_value_count(), 7);
+        tree.insert(1000, "new".to_string());
+        assert_eq!(tree.distinct_value_count(), 8);
+        tree.retain_mut(|k, _| *k < 3);
+        assert_eq!(tree.distinct_value_count(), 3);
+    }
+
+    #[test]
+    fn range_first_and_last_bounds() {
+        let tree = Tree::with_sorted_keys((0..50).map(|i| i * 2), ());
+        let bounds = [Bound::Included(10), Bound::Excluded(10),
+                      Bound::Included(11), Bound::Excluded(11),
+                      Bound::Included(-5), Bound::Excluded(98),
+                      Bound::Included(98), Bound::Unbounded];
+        for &lo in &bounds {
+            for &hi in &bounds {
+                let mut inside = tree.iter()
+                                     .map(|(k, _)| *k)
+                                     .filter(|k| (lo, hi).contains(k));
+                let expected_first = inside.next();
+                let expected_last  = inside.last().or(expected_first);
+                let first = tree.range_first((lo, hi)).map(|(k, _)| *k);
+                let last  = tree.range_last((lo, hi)).map(|(k, _)| *k);
+                assert_eq!(first, expected_first);
+                assert_eq!(last, expected_last);
+            }
+        }
+        assert_eq!(tree.range_first(11..12), None);
+        assert_eq!(tree.range_last(..=-1), None);
+    }
+
+    #[test]
+    fn merged_prefers_self_on_overlap() {
+        let evens = Tree::with_sorted_keys((0..40).step_by(2), "even");
+        let triples = Tree::with_sorted_keys((0..40).step_by(3), "triple");
+
+        let merged = evens.merged(&triples).collect::<Vec<_>>();
+        let keys = merged.iter().map(|(k, _)| **k).collect::<Vec<_>>();
+        let expected = (0..40).filter(|k| k % 2 == 0 || k % 3 == 0)
+                              .collect::<Vec<_>>();
+        assert_eq!(keys, expected);
+        for (k, v) in merged {
+            assert_eq!(*v, if k % 2 == 0 { "even" } else { "triple" });
+        }
+
+        let empty = Tree::new();
+        assert!(empty.merged(&empty).next().is_none());
+        assert!(evens.merged(&empty).eq(evens.iter()));
+        assert!(empty.merged(&evens).eq(evens.iter()));
+    }
+
+    #[test]
+    fn any_in_range_stops_at_first_match() {
+        let tree = Tree::with_sorted_keys(0..100, ());
+
+        let mut visited = Vec::new();
+        assert!(tree.any_in_range(20..80, |k, _| {
+            visited.push(*k);
+            k % 7 == 0
+        }));
+        assert_eq!(visited, [20, 21]);
+
+        visited.clear();
+        assert!(!tree.any_in_range((Bound::Excluded(90), Bound::Included(95)),
+                                   |k, _| { visited.push(*k); false }));
+        assert_eq!(visited, [91, 92, 93, 94, 95]);
+
+        assert!(!tree.any_in_range(200.., |_, _| true));
+        assert!(!tree.any_in_range(50..50, |_, _| true));
+    }
+
+    #[test]
+    fn nth_value_over_multiset() {
+        let values = [5, 3, 9, 3, 7, 1, 5, 5, 8, 0];
+        let mut tree = Tree::new();
+        for (k, v) in values.iter().enumerate() {
+            tree.insert(k, *v);
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_unstable();
+        for (n, v) in sorted.iter().enumerate() {
+            assert_eq!(tree.nth_value(n), Some(v));
+        }
+        assert_eq!(tree.nth_value(values.len()), None);
+        assert_eq!(Tree::<i32, i32>::new().nth_value(0), None);
+    }
+
+    #[test]
+    fn floor_and_ceiling_ranks_match_enumeration() {
+        let tree = Tree::with_sorted_keys((0..60).map(|i| i * 3), ());
+        let keys = tree.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        for q in -2..185 {
+            let floor   = keys.iter().rposition(|k| *k <= q);
+            let ceiling = keys.iter().position(|k| *k >= q);
+            assert_eq!(tree.floor_rank(&q), floor);
+            assert_eq!(tree.ceiling_rank(&q), ceiling);
+            if let Some(i) = floor {
+                assert_eq!(tree.get_nth(i), tree.floor(&q));
+            }
+            if let Some(i) = ceiling {
+                assert_eq!(tree.get_nth(i), tree.ceiling(&q));
+            }
+        }
+        let empty = Tree::<i32, ()>::new();
+        assert_eq!(empty.floor_rank(&0), None);
+        assert_eq!(empty.ceiling_rank(&0), None);
+    }
+
+    #[test]
+    fn relaxed_tree_height_stays_bounded() {
+        let mut strict  = Tree::new();
+        let mut relaxed = Tree::with_imbalance_tolerance(2);
+        for i in 0..1000 {
+            let k = i * 7919 % 1000;
+            strict.insert(k, i);
+            relaxed.insert(k, i);
+        }
+        for k in (0..1000).step_by(3) {
+            assert_eq!(relaxed.remove(&k), strict.take(&k));
+        }
+        assert!(relaxed.iter().eq(strict.iter()));
+        assert!(relaxed.verify_weights());
+        assert!(max_depth(&relaxed) <= 2 * max_depth(&strict));
+
+        let mut sorted = Tree::with_imbalance_tolerance(1);
+        for i in 0..1000 {
+            sorted.insert(i, ());
+        }
+        assert!(is_balanced(&sorted));
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn relaxed_tree_rotates_less() {
+        let mut strict  = Tree::new();
+        let mut relaxed = Tree::with_imbalance_tolerance(2);
+        let mut same    = Tree::with_imbalance_tolerance(1);
+        for i in 0..1000 {
+            strict.insert(i, ());
+            relaxed.insert(i, ());
+            same.insert(i, ());
+        }
+        assert!(relaxed.rotation_count() < strict.rotation_count());
+        assert_eq!(same.rotation_count(), strict.rotation_count());
+        assert_eq!(*same, strict);
+    }
+
+    #[test]
+    fn retain_keys_in_masks_by_smaller_set() {
+        let mut tree = Tree::new();
+        for i in 0..200 {
+            tree.insert(i, i.to_string());
+        }
+        let mut mask = Tree::new();
+        for k in &[-5, 0, 3, 64, 65, 150, 199, 250] {
+            mask.insert(*k, vec![*k as u8]);
+        }
+        tree.retain_keys_in(&mask);
+        let keys = tree.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys, [0, 3, 64, 65, 150, 199]);
+        assert_eq!(tree[&64], "64");
+        assert!(is_balanced(&tree));
+
+        tree.retain_keys_in(&Tree::<i32, ()>::new());
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn remove_nth_reporting_tracks_length() {
+        let mut tree = Tree::from_sorted_slice(&[(1, 'a'), (2, 'b'), (3, 'c')]);
+        assert_eq!(tree.remove_nth_reporting(0), Some(((1, 'a'), 2)));
+        assert_eq!(tree.remove_nth_reporting(0), Some(((2, 'b'), 1)));
+        assert_eq!(tree.remove_nth_reporting(1), None);
+        assert_eq!(tree.remove_nth_reporting(0), Some(((3, 'c'), 0)));
+        assert_eq!(tree.remove_nth_reporting(0), None);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn eq_ignoring_skips_listed_keys() {
+        let base = Tree::from_sorted_slice(&[("a", 1), ("b", 2), ("c", 3)]);
+        let mut other = base.clone();
+        other.insert("b", 20);
+        assert!(base.eq_ignoring(&other, &["b"]));
+        assert!(!base.eq_ignoring(&other, &["a", "c"]));
+
+        other.insert("c", 30);
+        assert!(!base.eq_ignoring(&other, &["b"]));
+        assert!(base.eq_ignoring(&other, &["c", "b"]));
+
+        other.insert("d", 4);
+        assert!(!base.eq_ignoring(&other, &["b", "c"]));
+        assert!(base.eq_ignoring(&other, &["b", "c", "d"]));
+        assert!(other.eq_ignoring(&base, &["b", "c", "d"]));
+    }
+
+    #[test]
+    fn neighbors_at_extremes_and_gaps() {
+        let tree = Tree::with_sorted_keys((1..=15).map(|i| i * 10), ());
+        let key  = |e: Option<(&i32, &())>| e.map(|(k, _)| *k);
+        let keys = |q: i32| {
+            let (p, e, n) = tree.neighbors(&q);
+            (key(p), key(e), key(n))
+        };
+        assert_eq!(keys(80), (Some(70), Some(80), Some(90)));
+        assert_eq!(keys(85), (Some(80), None, Some(90)));
+        assert_eq!(keys(10), (None, Some(10), Some(20)));
+        assert_eq!(keys(150), (Some(140), Some(150), None));
+        assert_eq!(keys(5), (None, None, Some(10)));
+        assert_eq!(keys(155), (Some(150), None, None));
+        for q in 0..160 {
+            let (p, e, n) = keys(q);
+            let before = tree.iter().map(|(k, _)| *k).filter(|k| *k < q);
+            assert_eq!(p, before.last());
+            assert_eq!(e, tree.get(&q).map(|_| q));
+            assert_eq!(n, tree.iter().map(|(k, _)| *k).find(|k| *k > q));
+        }
+        assert_eq!(Tree::<i32, ()>::new().neighbors(&0), (None, None, None));
+    }
+
+    #[test]
+    fn concat_ordered_trees() {
+        for &(n, m) in &[(0, 0), (0, 7), (7, 0), (1, 300), (300, 1),
+                         (40, 600), (600, 40), (255, 256)] {
+            let lower = (0..n).map(|i| (i, i * 2)).collect::<Vec<_>>();
+            let upper = (n..n + m).map(|i| (i, i * 2)).collect::<Vec<_>>();
+
+            let mut tree = Tree::from_sorted_slice(&lower);
+            tree.concat(Tree::from_sorted_slice(&upper));
+            assert_eq!(tree.len(), n + m);
+            assert!(tree.iter().map(|(k, v)| (*k, *v))
+                               .eq((0..n + m).map(|i| (i, i * 2))));
+            assert!(is_balanced(&tree));
+            assert!(tree.verify_weights());
+        }
+    }
+
+    #[test]
+    fn count_by_buckets_keys_mod_three() {
+        let tree = Tree::with_sorted_keys(0..100, ());
+        let counts = tree.count_by(|k| k % 3);
+        assert_eq!(counts.into_iter().collect::<Vec<_>>(),
+                   [(0, 34), (1, 33), (2, 33)]);
+
+        let evens = tree.count_by(|k| k % 2 == 0);
+        assert_eq!(evens[&true], 50);
+        assert_eq!(evens[&false], 50);
+        assert!(Tree::<i32, ()>::new().count_by(|k| *k).is_empty());
+    }
+
+    #[test]
+    fn iter_by_value_ascends_with_key_ties() {
+        let mut tree = Tree::new();
+        for i in 0..100 {
+            tree.insert(i, (i * 37) % 10);
+        }
+        let entries = tree.iter_by_value().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 100);
+        assert!(entries.windows(2).all(|w| w[0].1 < w[1].1
+                                           || w[0].1 == w[1].1
+                                              && w[0].0 < w[1].0));
+        assert_eq!(entries[0], (&0, &0));
+        assert_eq!(entries[99], (&97, &9));
+    }
+
+    #[test]
+    fn rotations_pass_debug_invariants() {
+        let node = |key, left, right| {
+            Filled(Box::new(Node::with_children(key, (), left, right)))
+        };
+        let leaf = |key| Filled(Box::new(Node::new(key, ())));
+        type Rotation = fn(&mut Link<i32, ()>, &mut Stats);
+        let cases: [(Rotation, Link<i32, ()>); 4] = [
+            (Link::rotate_left_left,   node(3, node(2, leaf(1), Empty), Empty)),
+            (Link::rotate_right_right, node(1, Empty, node(2, Empty, leaf(3)))),
+            (Link::rotate_left_right,  node(3, node(1, Empty, leaf(2)), Empty)),
+            (Link::rotate_right_left,  node(1, Empty, node(3, leaf(2), Empty))),
+        ];
+        for (rotate, root) in cases.iter() {
+            let mut tree = Tree::from_root(root.clone());
+            assert!(tree.verify_weights());
+            assert_eq!(tree.balance().abs(), 2);
+            rotate(&mut tree.root, &mut tree.stats);
+            assert!(tree.root.rotated_sound(true));
+            assert_eq!(tree.balance(), 0);
+            assert_eq!(tree.key, 2);
+            assert!(tree.iter().map(|(k, _)| *k).eq(1..=3));
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Left-left rotation failed.")]
+    fn unbalancing_rotation_fails_debug_invariants() {
+        let node = |key, left, right| {
+            Filled(Box::new(Node::with_children(key, (), left, right)))
+        };
+        let leaf = |key| Filled(Box::new(Node::new(key, ())));
+        let mut tree = node(3, node(1, Empty, leaf(2)), Empty);
+        assert_eq!(tree.balance(), 2);
+        tree.rotate_left_left(&mut Stats::default());
+    }
+
+    #[test]
+    fn remove_keeps_tree_balanced() {
+        let mut tree = Tree::new();
+        let mut seed = 0x2545_f491_u64;
+        let mut keys = Vec::new();
+        while keys.len() < 300 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let key = seed % 500;
+            if tree.insert(key, key * 2).is_none() {
+                keys.push(key);
+            }
+        }
+        for key in keys.iter().take(200) {
+            assert_eq!(tree.remove(key), Some(key * 2));
+            assert!(tree.balance_histogram().is_some());
+        }
+        assert_eq!(tree.len(), 100);
+        assert!(keys[200..].iter().all(|k| tree.get(k) == Some(&(k * 2))));
+    }
+
+    #[test]
+    fn to_dot_declares_each_node() {
+        let tree = Tree::with_sorted_keys(0..7, ());
+        let dot  = tree.to_dot();
+        let decls = dot.lines().filter(|l| l.contains("[label=\"")
+                                           && !l.contains("->")).count();
+        let edges = dot.lines().filter(|l| l.contains("->")).count();
+        assert_eq!(decls, 7);
+        assert_eq!(edges, 6);
+        assert_eq!(dot.matches("[label=\"L\"]").count(), 3);
+        assert!(dot.ends_with("}\n"));
+
+        let quoted = Tree::new_with_insert("say \"hi\"", ());
+        assert!(quoted.to_dot().contains(r#"label="say \"hi\"\nbf=0""#));
+        assert_eq!(Tree::<i32, ()>::new().to_dot(), "digraph Tree {\n}\n");
+    }
+
+    #[test]
+    fn drain_range_middle_of_tree() {
+        let mut tree = Tree::new();
+        for i in 0..200 {
+            tree.insert(i, i * 10);
+        }
+        let drained = tree.drain_range(50..=120).collect::<Vec<_>>();
+        assert!(drained.into_iter().eq((50..=120).map(|i| (i, i * 10))));
+        assert!(tree.iter().map(|(k, v)| (*k, *v))
+                           .eq((0..50).chain(121..200).map(|i| (i, i * 10))));
+        assert!(is_balanced(&tree));
+        assert!(tree.verify_weights());
+
+        let excluded = (Bound::Excluded(10), Bound::Excluded(13));
+        let keys = tree.drain_range(excluded).map(|(k, _)| k);
+        assert_eq!(keys.collect::<Vec<_>>(), [11, 12]);
+        assert_eq!(tree.drain_range(60..100).count(), 0);
+        assert_eq!(tree.len(), 200 - 71 - 2);
+
+        assert_eq!(tree.drain_range(..).count(), 127);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn leaf_depth_gap_stays_small() {
+        let mut tree = Tree::new();
+        let mut seed = 0x2545_f491_u64;
+        for round in 0..20_000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let key = seed % 2000;
+            if round % 3 == 2 {
+                tree.remove(&key);
+            } else {
+                tree.insert(key, ());
+            }
+            if round % 500 == 0 {
+                let (lo, hi) = tree.leaf_depth_range().unwrap();
+                assert!(lo <= hi && hi <= 2 * lo + 1);
+                assert_eq!(hi, tree.height_report().0);
+            }
+        }
+        let sorted = Tree::with_sorted_keys(0..1023, ());
+        assert_eq!(sorted.leaf_depth_range(), Some((9, 9)));
     }
 }
 
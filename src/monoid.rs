@@ -0,0 +1,620 @@
+//! A variant of the AVL tree whose nodes also hold an aggregate of the values
+//! in their sub-trees, combined by a user supplied associative operation. The
+//! aggregates are kept up to date through insertions, removals and rotations,
+//! which lets the aggregate of any range of keys be found in `O(log n)` time.
+//!
+
+use std::cmp::Ordering;
+use std::ops::Bound;
+use std::ops::RangeBounds;
+
+use crate::after_start;
+use crate::before_end;
+
+type Link<K, V> = Option<Box<MonoidNode<K, V>>>;
+
+/// A node of a `MonoidTree`. Alongside its key and value, it holds the
+/// aggregate of every value in its sub-tree, in key order.
+///
+#[derive(Clone, Debug)]
+struct MonoidNode<K, V>
+{
+    key     : K,
+    value   : V,
+    agg     : V,
+    weight  : usize,
+    height  : usize,
+    left    : Link<K, V>,
+    right   : Link<K, V>,
+}
+
+/// The identity value and combining operation of a `MonoidTree`.
+///
+#[derive(Clone)]
+struct Monoid<V, M>
+{
+    identity : V,
+    combine  : M,
+}
+
+/// An AVL tree that maintains, at every node, the aggregate of the values in
+/// its sub-tree. The aggregate is built with `combine`, which must be
+/// associative, and `identity`, which must leave any value unchanged when
+/// combined with it. The operation needn't be commutative; values are always
+/// combined in ascending key order. Sums, minimums and maximums are typical.
+/// Lookups, insertions and removals are `O(log n)`, as is
+/// `.range_aggregate()`. Created by `MonoidTree::new()`.
+/// ```
+/// use avl_tree::*;
+///
+/// let mut tree = MonoidTree::new(0, |a: &i32, b: &i32| a + b);
+/// for i in 1..=10 {
+///     tree.insert(i, i * i);
+/// }
+/// assert_eq!(tree.range_aggregate(2..=4), 4 + 9 + 16);
+/// ```
+///
+#[derive(Clone)]
+pub struct MonoidTree<K, V, M>
+{
+    root   : Link<K, V>,
+    monoid : Monoid<V, M>,
+}
+
+impl<K, V, M> MonoidTree<K, V, M>
+where
+    K: Ord,
+    V: Clone,
+    M: Fn(&V, &V) -> V,
+{
+    /// Creates a new empty `MonoidTree` that aggregates its values with
+    /// `combine`, starting from `identity`.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let tree = MonoidTree::<i32, _, _>::new(i64::MIN, |a, b| *a.max(b));
+    /// assert_eq!(tree.aggregate(), i64::MIN);
+    /// ```
+    ///
+    pub fn new(identity: V, combine: M) -> Self
+    {
+        MonoidTree { root: None, monoid: Monoid { identity, combine } }
+    }
+
+    /// Indicates whether the tree is empty.
+    ///
+    pub fn is_empty(&self) -> bool
+    {
+        self.root.is_none()
+    }
+
+    /// Returns the number of items in the tree.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = MonoidTree::new(0, |a: &u32, b: &u32| a + b);
+    /// tree.insert('a', 1);
+    /// tree.insert('b', 2);
+    /// assert_eq!(tree.len(), 2);
+    /// ```
+    ///
+    pub fn len(&self) -> usize
+    {
+        weight(&self.root)
+    }
+
+    /// Retrieves the value associated with the given key, or `None` if the key
+    /// isn't in the tree.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = MonoidTree::new(0, |a: &i32, b: &i32| a + b);
+    /// tree.insert(77, 88);
+    /// assert_eq!(tree.get(&77), Some(&88));
+    /// assert_eq!(tree.get(&100), None);
+    /// ```
+    ///
+    pub fn get(&self, key: &K) -> Option<&V>
+    {
+        use Ordering::*;
+        let mut link = &self.root;
+        while let Some(node) = link {
+            match key.cmp(&node.key) {
+                Less    => link = &node.left,
+                Greater => link = &node.right,
+                Equal   => return Some(&node.value),
+            }
+        }
+        None
+    }
+
+    /// Inserts the given key and value into the tree, updating the aggregates
+    /// along the path to the key. Returns the former value of the key if it
+    /// was already present.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = MonoidTree::new(0, |a: &i32, b: &i32| a + b);
+    /// assert_eq!(tree.insert("foo", 42), None);
+    /// assert_eq!(tree.insert("foo", 72), Some(42));
+    /// assert_eq!(tree.aggregate(), 72);
+    /// ```
+    ///
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    {
+        insert(&mut self.root, key, value, &self.monoid)
+    }
+
+    /// Removes the given key from the tree, updating the aggregates along the
+    /// path to the key. Returns the former value of the key if it was present.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = MonoidTree::new(0, |a: &i32, b: &i32| a + b);
+    /// tree.insert("foo", 42);
+    /// assert_eq!(tree.remove(&"foo"), Some(42));
+    /// assert_eq!(tree.remove(&"foo"), None);
+    /// assert_eq!(tree.aggregate(), 0);
+    /// ```
+    ///
+    pub fn remove(&mut self, key: &K) -> Option<V>
+    {
+        remove(&mut self.root, key, &self.monoid)
+    }
+
+    /// Returns the aggregate of every value in the tree, or the identity if
+    /// the tree is empty. This is read directly from the root in `O(1)` time,
+    /// apart from the cost of cloning the value.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = MonoidTree::new(0, |a: &i32, b: &i32| a + b);
+    /// tree.insert(1, 10);
+    /// tree.insert(2, 20);
+    /// assert_eq!(tree.aggregate(), 30);
+    /// ```
+    ///
+    pub fn aggregate(&self) -> V
+    {
+        self.monoid.agg(&self.root).clone()
+    }
+
+    /// Returns the aggregate of the values whose keys fall within `range`, or
+    /// the identity if there are none. The search splits at the highest node
+    /// within the range, after which each side follows a single path, taking
+    /// the stored aggregates of the sub-trees wholly inside the range. This
+    /// makes it an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = MonoidTree::new(i32::MIN, |a: &i32, b: &i32| *a.max(b));
+    /// for (k, v) in [(1, 5), (2, 9), (3, 2), (4, 7)].iter() {
+    ///     tree.insert(*k, *v);
+    /// }
+    /// assert_eq!(tree.range_aggregate(3..), 7);
+    /// assert_eq!(tree.range_aggregate(..3), 9);
+    /// assert_eq!(tree.range_aggregate(5..), i32::MIN);
+    /// ```
+    ///
+    pub fn range_aggregate<R>(&self, range: R) -> V
+    where
+        R: RangeBounds<K>,
+    {
+        range_agg(&self.root, range.start_bound(), range.end_bound(),
+                  &self.monoid)
+    }
+
+    /// Returns the aggregate of the values whose keys are less than or equal to
+    /// `key`, or the identity if there are none. With a monoid of addition,
+    /// this is the prefix sum up to `key`. Like `.range_aggregate()`, this is
+    /// an `O(log n)` operation.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = MonoidTree::new(0, |a: &i32, b: &i32| a + b);
+    /// for (k, v) in [(1, 10), (2, 20), (4, 40)].iter() {
+    ///     tree.insert(*k, *v);
+    /// }
+    /// assert_eq!(tree.prefix_aggregate(&3), 30);
+    /// assert_eq!(tree.prefix_aggregate(&0), 0);
+    /// ```
+    ///
+    pub fn prefix_aggregate(&self, key: &K) -> V
+    {
+        self.range_aggregate((Bound::Unbounded, Bound::Included(key)))
+    }
+
+    /// Returns an iterator over the keys and values of the tree in ascending
+    /// order by key.
+    /// ```
+    /// use avl_tree::*;
+    ///
+    /// let mut tree = MonoidTree::new(0, |a: &i32, b: &i32| a + b);
+    /// tree.insert(2, 20);
+    /// tree.insert(1, 10);
+    /// let keys = tree.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+    /// assert_eq!(keys, [1, 2]);
+    /// ```
+    ///
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)>
+    {
+        let mut stack = Vec::new();
+        push_left(&mut stack, &self.root);
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            push_left(&mut stack, &node.right);
+            Some((&node.key, &node.value))
+        })
+    }
+}
+
+impl<V, M> Monoid<V, M>
+where
+    M: Fn(&V, &V) -> V,
+{
+    /// Returns the aggregate of the sub-tree at `link`, which is the identity
+    /// if it's empty.
+    ///
+    fn agg<'a, K>(&'a self, link: &'a Link<K, V>) -> &'a V
+    {
+        link.as_ref().map_or(&self.identity, |node| &node.agg)
+    }
+
+    /// Combines the aggregate of `left`, then `value`, then the aggregate of
+    /// `right`, in that order.
+    ///
+    fn combine3<K>(&self, left: &Link<K, V>, value: &V, right: &Link<K, V>)
+        -> V
+    {
+        let lv = (self.combine)(self.agg(left), value);
+        (self.combine)(&lv, self.agg(right))
+    }
+}
+
+/// Pushes the node of the given link and all the nodes along its left spine
+/// onto the stack.
+///
+fn push_left<'a, K, V>(stack: &mut Vec<&'a MonoidNode<K, V>>,
+                       mut link: &'a Link<K, V>)
+{
+    while let Some(node) = link {
+        stack.push(node);
+        link = &node.left;
+    }
+}
+
+/// Returns the weight (number of nodes) of the sub-tree at `link`.
+///
+fn weight<K, V>(link: &Link<K, V>) -> usize
+{
+    link.as_ref().map_or(0, |node| node.weight)
+}
+
+/// Returns the height of the sub-tree at `link`, which is 0 if it's empty and
+/// 1 for a leaf.
+///
+fn height<K, V>(link: &Link<K, V>) -> usize
+{
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+/// Returns the difference in height between the left and right sub-trees of
+/// the node at `link`.
+///
+fn balance<K, V>(link: &Link<K, V>) -> isize
+{
+    match link {
+        Some(node) => {
+            height(&node.left) as isize - height(&node.right) as isize
+        },
+        None => 0,
+    }
+}
+
+impl<K, V> MonoidNode<K, V>
+{
+    /// Recalculates the weight, height and aggregate of the node from its
+    /// children.
+    ///
+    fn update<M>(&mut self, monoid: &Monoid<V, M>)
+    where
+        M: Fn(&V, &V) -> V,
+    {
+        self.weight = 1 + weight(&self.left) + weight(&self.right);
+        self.height = 1 + height(&self.left).max(height(&self.right));
+        self.agg    = monoid.combine3(&self.left, &self.value, &self.right);
+    }
+}
+
+/// Rotates the sub-tree at `link` to the right, making its left child the new
+/// root of the sub-tree.
+///
+fn rotate_right<K, V, M>(link: &mut Link<K, V>, monoid: &Monoid<V, M>)
+where
+    M: Fn(&V, &V) -> V,
+{
+    let mut n = link.take().expect("Rotation of an empty link.");
+    let mut l = n.left.take().expect("Rotation without a left child.");
+    n.left    = l.right.take();
+    n.update(monoid);
+    l.right   = Some(n);
+    l.update(monoid);
+    *link     = Some(l);
+}
+
+/// Rotates the sub-tree at `link` to the left, making its right child the new
+/// root of the sub-tree.
+///
+fn rotate_left<K, V, M>(link: &mut Link<K, V>, monoid: &Monoid<V, M>)
+where
+    M: Fn(&V, &V) -> V,
+{
+    let mut n = link.take().expect("Rotation of an empty link.");
+    let mut r = n.right.take().expect("Rotation without a right child.");
+    n.right   = r.left.take();
+    n.update(monoid);
+    r.left    = Some(n);
+    r.update(monoid);
+    *link     = Some(r);
+}
+
+/// Updates the node at `link` and performs the single or double rotation
+/// needed to restore its balance.
+///
+fn rebalance<K, V, M>(link: &mut Link<K, V>, monoid: &Monoid<V, M>)
+where
+    M: Fn(&V, &V) -> V,
+{
+    if let Some(node) = link {
+        node.update(monoid);
+    }
+    let bf = balance(link);
+    if bf >= 2 {
+        let node = link.as_mut().unwrap();
+        if balance(&node.left) < 0 {
+            rotate_left(&mut node.left, monoid);
+        }
+        rotate_right(link, monoid);
+    }
+    else if bf <= -2 {
+        let node = link.as_mut().unwrap();
+        if balance(&node.right) > 0 {
+            rotate_right(&mut node.right, monoid);
+        }
+        rotate_left(link, monoid);
+    }
+}
+
+/// Inserts the key and value into the sub-tree at `link`. See
+/// `MonoidTree::insert()`.
+///
+fn insert<K, V, M>(link   : &mut Link<K, V>,
+                   key    : K,
+                   value  : V,
+                   monoid : &Monoid<V, M>) -> Option<V>
+where
+    K: Ord,
+    V: Clone,
+    M: Fn(&V, &V) -> V,
+{
+    use Ordering::*;
+    let node = match link {
+        Some(node) => node,
+        None => {
+            let agg = value.clone();
+            *link = Some(Box::new(MonoidNode { key, value, agg,
+                                               weight: 1, height: 1,
+                                               left: None, right: None }));
+            return None;
+        },
+    };
+    let ret = match key.cmp(&node.key) {
+        Less    => insert(&mut node.left, key, value, monoid),
+        Greater => insert(&mut node.right, key, value, monoid),
+        Equal   => Some(std::mem::replace(&mut node.value, value)),
+    };
+    rebalance(link, monoid);
+    ret
+}
+
+/// Removes the key from the sub-tree at `link`. See `MonoidTree::remove()`.
+///
+fn remove<K, V, M>(link: &mut Link<K, V>, key: &K, monoid: &Monoid<V, M>)
+    -> Option<V>
+where
+    K: Ord,
+    M: Fn(&V, &V) -> V,
+{
+    use Ordering::*;
+    let node = link.as_mut()?;
+    let ret  = match key.cmp(&node.key) {
+        Less    => remove(&mut node.left, key, monoid),
+        Greater => remove(&mut node.right, key, monoid),
+        Equal   => {
+            if node.right.is_some() {
+                let (k, v) = remove_min(&mut node.right, monoid);
+                node.key   = k;
+                Some(std::mem::replace(&mut node.value, v))
+            } else {
+                let left = node.left.take();
+                let old  = std::mem::replace(link, left);
+                return old.map(|node| node.value);
+            }
+        },
+    };
+    if ret.is_some() {
+        rebalance(link, monoid);
+    }
+    ret
+}
+
+/// Removes the smallest key from the non-empty sub-tree at `link` and returns
+/// it along with its value.
+///
+fn remove_min<K, V, M>(link: &mut Link<K, V>, monoid: &Monoid<V, M>) -> (K, V)
+where
+    M: Fn(&V, &V) -> V,
+{
+    let node = link.as_mut().expect("Empty link.");
+    if node.left.is_some() {
+        let ret = remove_min(&mut node.left, monoid);
+        rebalance(link, monoid);
+        ret
+    } else {
+        let right = node.right.take();
+        let old   = std::mem::replace(link, right).unwrap();
+        (old.key, old.value)
+    }
+}
+
+/// Returns the aggregate of the values in the sub-tree at `link` whose keys
+/// are within `start` and `end`. Once the search reaches a node within both
+/// bounds, its left side only needs the lower bound and its right side only
+/// the upper, so each side is a single path. See
+/// `MonoidTree::range_aggregate()`.
+///
+fn range_agg<K, V, M>(link   : &Link<K, V>,
+                      start  : Bound<&K>,
+                      end    : Bound<&K>,
+                      monoid : &Monoid<V, M>) -> V
+where
+    K: Ord,
+    V: Clone,
+    M: Fn(&V, &V) -> V,
+{
+    use Bound::*;
+    let node = match link {
+        Some(node) => node,
+        None       => return monoid.identity.clone(),
+    };
+    if let (Unbounded, Unbounded) = (start, end) {
+        node.agg.clone()
+    } else if !after_start(start, &node.key) {
+        range_agg(&node.right, start, end, monoid)
+    } else if !before_end(end, &node.key) {
+        range_agg(&node.left, start, end, monoid)
+    } else {
+        let l  = range_agg(&node.left, start, Unbounded, monoid);
+        let r  = range_agg(&node.right, Unbounded, end, monoid);
+        let lv = (monoid.combine)(&l, &node.value);
+        (monoid.combine)(&lv, &r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Tree;
+    use super::*;
+
+    fn is_valid<K, V, M>(link: &Link<K, V>, monoid: &Monoid<V, M>) -> bool
+    where
+        K: Ord,
+        V: PartialEq,
+        M: Fn(&V, &V) -> V,
+    {
+        match link {
+            Some(node) => {
+                node.weight == 1 + weight(&node.left) + weight(&node.right)
+                && node.height == 1 + height(&node.left)
+                                        .max(height(&node.right))
+                && node.agg == monoid.combine3(&node.left, &node.value,
+                                               &node.right)
+                && balance(link).abs() <= 1
+                && is_valid(&node.left, monoid)
+                && is_valid(&node.right, monoid)
+            },
+            None => true,
+        }
+    }
+
+    #[test]
+    fn range_sum_matches_brute_force() {
+        let mut tree  = MonoidTree::new(0i64, |a: &i64, b: &i64| a + b);
+        let mut naive = Tree::new();
+        for i in 0..300i64 {
+            let k = i * 7919 % 300;
+            tree.insert(k, i - 150);
+            naive.insert(k, i - 150);
+        }
+        for k in (0..300).step_by(4) {
+            assert_eq!(tree.remove(&k), naive.remove(&k));
+        }
+        assert!(is_valid(&tree.root, &tree.monoid));
+        assert_eq!(tree.len(), naive.len());
+
+        for lo in (-10..310).step_by(13) {
+            for hi in (lo..310).step_by(17) {
+                let expected = naive.iter()
+                                    .filter(|(k, _)| (lo..hi).contains(*k))
+                                    .map(|(_, v)| *v)
+                                    .sum::<i64>();
+                assert_eq!(tree.range_aggregate(lo..hi), expected);
+            }
+        }
+        let total = naive.iter().map(|(_, v)| *v).sum::<i64>();
+        assert_eq!(tree.aggregate(), total);
+        assert_eq!(tree.range_aggregate(..), total);
+    }
+
+    #[test]
+    fn range_max_matches_brute_force() {
+        let mut tree  = MonoidTree::new(None, |a: &Option<u32>,
+                                               b: &Option<u32>| (*a).max(*b));
+        let mut naive = Tree::new();
+        for i in 0..200u32 {
+            let k = i * 37 % 200;
+            let v = i * 7 % 101;
+            tree.insert(k, Some(v));
+            naive.insert(k, v);
+        }
+        for k in (0..200).step_by(3) {
+            tree.remove(&k);
+            naive.remove(&k);
+        }
+        tree.insert(50, Some(1000));
+        naive.insert(50, 1000);
+        assert!(is_valid(&tree.root, &tree.monoid));
+
+        let bounds = [Bound::Included(0), Bound::Excluded(50),
+                      Bound::Included(50), Bound::Included(120),
+                      Bound::Excluded(199), Bound::Unbounded];
+        for &lo in &bounds {
+            for &hi in &bounds {
+                let expected = naive.iter()
+                                    .filter(|(k, _)| (lo, hi).contains(*k))
+                                    .map(|(_, v)| *v)
+                                    .max();
+                assert_eq!(tree.range_aggregate((lo, hi)), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn prefix_aggregate_matches_manual_sums() {
+        let mut tree  = MonoidTree::new(0i64, |a: &i64, b: &i64| a + b);
+        let mut naive = Tree::new();
+        for i in 0..200i64 {
+            tree.insert((i * 37) % 400, i);
+            naive.insert((i * 37) % 400, i);
+        }
+        for q in -1..401 {
+            let manual = naive.iter()
+                              .filter(|(k, _)| **k <= q)
+                              .map(|(_, v)| *v)
+                              .sum::<i64>();
+            assert_eq!(tree.prefix_aggregate(&q), manual);
+        }
+    }
+
+    #[test]
+    fn aggregate_keeps_key_order() {
+        let mut tree = MonoidTree::new(String::new(),
+                                       |a: &String, b: &String| a.clone() + b);
+        for ch in "monoid".chars() {
+            tree.insert(ch, ch.to_string());
+        }
+        assert!(is_valid(&tree.root, &tree.monoid));
+        assert_eq!(tree.aggregate(), "dimno");
+        assert_eq!(tree.range_aggregate('e'..'o'), "imn");
+    }
+}